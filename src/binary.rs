@@ -0,0 +1,27 @@
+use std::{fs::File, io::Read, path::Path};
+
+/// How many bytes from the start of a file are inspected to classify it.
+const SNIFF_LENGTH: usize = 8000;
+
+/// Whether a file is probably binary (rather than text), judging by its first
+/// bytes: binary files are those that contain a NUL byte, or that are not valid
+/// UTF-8.
+///
+/// Files that cannot be read are taken to be text, so that they are not
+/// silently dropped.
+pub fn is_probably_binary(path: &Path) -> bool {
+    let mut buffer = Vec::with_capacity(SNIFF_LENGTH);
+    let read =
+        File::open(path).and_then(|file| file.take(SNIFF_LENGTH as u64).read_to_end(&mut buffer));
+    if read.is_err() {
+        return false;
+    }
+    if buffer.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(&buffer) {
+        Ok(_) => false,
+        // A character may have been cut short at the end of the inspected bytes.
+        Err(err) => err.error_len().is_some(),
+    }
+}
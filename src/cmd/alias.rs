@@ -0,0 +1,97 @@
+use crate::{
+    cmd::find_or_exit,
+    config::{Config, ConfigStore},
+};
+use colored::Colorize;
+
+/// Makes `alias` refer to the template `template_name` (itself a name or an
+/// alias). An alias cannot shadow a template's name, nor be another alias.
+pub fn add(config: &mut impl ConfigStore, template_name: &str, alias: String) {
+    let alias = alias.trim().to_string();
+    if alias.is_empty() {
+        eprintln!("{}", "An alias cannot be empty.".red());
+        std::process::exit(exitcode::USAGE);
+    }
+    if config
+        .config()
+        .templates
+        .contains_key(&Config::get_template_key(&alias))
+    {
+        eprintln!(
+            "{}",
+            format!(
+                "{} is the name of a template, and cannot be an alias.",
+                alias
+            )
+            .red()
+        );
+        std::process::exit(exitcode::USAGE);
+    }
+    if let Some((_, aliased)) = config.config().find_alias(&alias) {
+        eprintln!(
+            "{}",
+            format!("{} is already an alias, of {}.", alias, aliased.name).red()
+        );
+        eprintln!(
+            "{} {}{}",
+            "You can remove it first with".dimmed(),
+            format!("boyl alias remove {}", alias).yellow(),
+            ".".dimmed()
+        );
+        std::process::exit(exitcode::USAGE);
+    }
+
+    let (template_key, template) = find_or_exit(config, template_name);
+    // Templates from the extra template directories are not saved, and neither
+    // would their aliases be.
+    if template.external {
+        eprintln!(
+            "{}",
+            format!(
+                "{} is found in {}, rather than registered, and cannot have aliases.",
+                template.name,
+                template.path.to_string_lossy()
+            )
+            .red()
+        );
+        std::process::exit(exitcode::USAGE);
+    }
+
+    eprintln!(
+        "{} {} {}",
+        alias.bold(),
+        "now refers to".dimmed(),
+        template.name.bold()
+    );
+    config
+        .config_mut()
+        .templates
+        .get_mut(&template_key)
+        .unwrap()
+        .aliases
+        .push(alias);
+}
+
+/// Removes `alias` from whichever template has it.
+pub fn remove(config: &mut impl ConfigStore, alias: &str) {
+    let alias = alias.trim();
+    let template_key = match config.config().find_alias(alias) {
+        Some((template_key, _)) => *template_key,
+        None => {
+            eprintln!("{}", format!("{} is not an existing alias.", alias).red());
+            std::process::exit(exitcode::USAGE);
+        }
+    };
+    let template = config
+        .config_mut()
+        .templates
+        .get_mut(&template_key)
+        .unwrap();
+    template.aliases.retain(|known| known != alias);
+    eprintln!(
+        "{} {} {}",
+        alias.bold(),
+        "no longer refers to".dimmed(),
+        template.name.bold()
+    );
+}
@@ -0,0 +1,13 @@
+use crate::{config::ConfigStore, userpath::UserDir};
+use colored::Colorize;
+
+/// Makes `location` the directory new projects are created in, when none is
+/// given. It is stored as given, which is already absolute and canonical.
+pub fn set_default_location(config: &mut impl ConfigStore, location: UserDir) {
+    eprintln!(
+        "{} {}",
+        "New projects will be created in".dimmed(),
+        location.to_string().bold()
+    );
+    config.config_mut().default_new_location = Some(location.path_buf);
+}
@@ -0,0 +1,34 @@
+use crate::{cmd::find_or_exit, config::ConfigStore, template::Template};
+use colored::Colorize;
+
+/// Sets the description of `template`; a blank `description` clears it.
+pub fn set_description(template: &mut Template, description: &str) {
+    template.description = if description.trim().is_empty() {
+        None
+    } else {
+        Some(description.to_string())
+    };
+}
+
+pub fn describe(config: &mut impl ConfigStore, template_name: &str, description: &str) {
+    let (template_key, _) = find_or_exit(config, template_name);
+
+    let template = config
+        .config_mut()
+        .templates
+        .get_mut(&template_key)
+        .unwrap();
+    set_description(template, description);
+    match &template.description {
+        Some(_) => eprintln!(
+            "{} {}",
+            "Updated the description of".dimmed(),
+            template.name.bold()
+        ),
+        None => eprintln!(
+            "{} {}",
+            "Cleared the description of".dimmed(),
+            template.name.bold()
+        ),
+    }
+}
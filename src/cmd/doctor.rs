@@ -0,0 +1,61 @@
+use crate::config::LoadedConfig;
+use colored::Colorize;
+
+/// Reports problems with the configuration; those that can be fixed automatically
+/// are fixed if `fix` is set, in which case the caller should save the configuration.
+pub fn doctor(config: &mut LoadedConfig, fix: bool) {
+    let mut healthy = true;
+
+    if fix {
+        for name in config.config.trim_names() {
+            println!(
+                "{} '{}'{}",
+                "Could not trim the name of".yellow(),
+                name,
+                ", as it is empty or taken by another template.".yellow()
+            );
+        }
+    }
+    for name in config.config.untrimmed_names() {
+        healthy = false;
+        println!(
+            "{} '{}'",
+            "This template name has surrounding whitespace:".yellow(),
+            name.bold()
+        );
+        println!(
+            "  {} {} {}",
+            "Call".dimmed(),
+            "boyl doctor --fix".yellow(),
+            "to remove it.".dimmed()
+        );
+    }
+
+    for (path, names) in config.config.shared_paths() {
+        healthy = false;
+        println!(
+            "{} {}",
+            "These templates share the same directory:".yellow(),
+            names.join(", ").bold()
+        );
+        println!("  {}", path.to_string_lossy().dimmed());
+        println!(
+            "  {}",
+            "Deleting any of them would delete the files of the others.".dimmed()
+        );
+    }
+
+    if healthy {
+        println!("{}", "No problems found.".green());
+    } else {
+        println!(
+            "{} {}",
+            "You can fix these problems by editing".dimmed(),
+            crate::config::get_json_path(&config.path).to_string_lossy()
+        );
+        if fix {
+            crate::config::write_config_or_fail(config);
+        }
+        std::process::exit(exitcode::DATAERR);
+    }
+}
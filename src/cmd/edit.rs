@@ -1,5 +1,7 @@
 use crate::{
-    config::{LoadedConfig, TemplateKey},
+    cmd::describe,
+    config::{Config, ConfigStore, TemplateKey, TrashedTemplate},
+    license,
     template::Template,
     ui::{self, input::InputField, layout::VisualBox, list::List, UiState, UiStateReaction},
 };
@@ -8,65 +10,92 @@ use tui::{
     backend::Backend,
     layout::Rect,
     style::{Color, Style},
-    text::{Span, Spans},
+    text::Spans,
     widgets::{Block, Borders, Paragraph},
 };
 
+/// Template fields that can be edited through the input prompt.
+#[derive(Clone, Copy)]
+enum EditField {
+    Name,
+    Description,
+    License,
+    Version,
+}
+
+/// A change to a template's description, license or version, made in this
+/// session, which is undone if the session is aborted.
+struct FieldEdit {
+    key: TemplateKey,
+    field: EditField,
+    /// The value of the field before the change.
+    previous: Option<String>,
+}
+
 enum EditUiMode {
     List,
     Delete(TemplateKey, String),
     Error(String),
-    Rename(TemplateKey),
+    Input(EditField, TemplateKey),
+    /// Typing a filter for the list, which narrows as it is typed.
+    Filter,
 }
 
-struct EditUi<'conf> {
-    config: &'conf mut LoadedConfig,
+struct EditUi<'conf, C: ConfigStore> {
+    config: &'conf mut C,
     mode: EditUiMode,
     list: List<'conf, Spans<'conf>>,
     input: InputField,
+    /// Only templates whose name or description contain this (ignoring case) are
+    /// listed.
+    filter: String,
+    /// The last template deleted in this session, which can be restored.
+    last_deleted: Option<TrashedTemplate>,
+    field_edits: Vec<FieldEdit>,
+    /// Whether the session was exited with `Ctrl+C`, discarding the field edits.
+    aborted: bool,
 }
 
-impl<'conf> EditUi<'conf> {
-    fn new(config: &'conf mut LoadedConfig) -> Self {
-        let list = List::new(Self::make_list_elements(config));
+impl<'conf, C: ConfigStore> EditUi<'conf, C> {
+    fn new(config: &'conf mut C) -> Self {
+        let list = List::new(Self::make_list_elements(config, ""));
         EditUi {
             config,
             mode: EditUiMode::List,
             list,
             input: InputField::new(),
+            filter: String::new(),
+            last_deleted: None,
+            field_edits: vec![],
+            aborted: false,
         }
     }
 
-    /// Computes a single `Spans`, corresponding to one entry on the list for a `Template`.
-    fn make_template_entry(template: &Template) -> Spans<'static> {
-        Spans::from(vec![
-            Span::raw(template.name.clone()),
-            Span::raw(" "),
-            Span::styled(
-                template
-                    .description
-                    .as_deref()
-                    .unwrap_or("(No description.)")
-                    .to_string(),
-                Style::default().fg(Color::Gray),
-            ),
-        ])
-    }
-
     /// Computes the `Spans` to display the existing templates in a list.
     ///
     /// This is a reasonably expensive operation, as it iterates over every
     /// template in `config` and clones the names and descriptions, so it
     /// should be used sparsely if possible.
-    fn make_list_elements(config: &LoadedConfig) -> Vec<Spans<'static>> {
-        config
-            .config
-            .templates
-            .values()
-            .map(Self::make_template_entry)
+    fn make_list_elements(config: &C, filter: &str) -> Vec<Spans<'static>> {
+        Self::visible_templates(config, filter)
+            .into_iter()
+            .map(|(_, template)| ui::template::template_entry(template))
             .collect::<Vec<Spans>>()
     }
 
+    /// The templates shown in the list, in order, given the filter.
+    fn visible_templates<'c>(config: &'c C, filter: &str) -> Vec<(TemplateKey, &'c Template)> {
+        config.config().templates_matching(filter)
+    }
+
+    /// Recomputes the list's elements (e.g., after the filter changed), keeping the
+    /// highlight within the list.
+    fn refresh_list(&mut self) {
+        let highlight = self.list.highlight;
+        self.list = List::new(Self::make_list_elements(self.config, &self.filter));
+        self.list.highlight = std::cmp::min(highlight, self.list.len().saturating_sub(1));
+    }
+
     fn list_input(&mut self, key: Key) -> Option<crate::ui::UiStateReaction> {
         match key {
             Key::Up | Key::Char('k') => {
@@ -75,46 +104,61 @@ impl<'conf> EditUi<'conf> {
             Key::Down | Key::Char('j') => {
                 self.list.go_down();
             }
-            Key::Ctrl('c') | Key::Char('q') | Key::Char('\n') | Key::Char('\r') => {
+            Key::PageUp => {
+                self.list.go_page_up(self.list.last_height());
+            }
+            Key::PageDown => {
+                self.list.go_page_down(self.list.last_height());
+            }
+            Key::Home => {
+                self.list.go_first();
+            }
+            Key::End => {
+                self.list.go_last();
+            }
+            Key::Char('q') | Key::Char('\n') | Key::Char('\r') => {
                 return Some(UiStateReaction::Exit);
             }
-            Key::Char('x') => {
-                if self.list.len() > 0 {
-                    let (&delete_key, template) = self
-                        .config
-                        .config
-                        .templates
-                        .iter()
-                        .nth(self.list.highlight)
-                        .unwrap();
-                    let delete_name = template.name.clone();
-                    self.mode = EditUiMode::Delete(delete_key, delete_name);
-                }
+            Key::Ctrl('c') => {
+                self.aborted = true;
+                return Some(UiStateReaction::Exit);
             }
-            Key::Char('e') => {
-                if self.list.len() > 0 {
-                    let rename_key = *self
-                        .config
-                        .config
-                        .templates
-                        .keys()
-                        .nth(self.list.highlight)
-                        .unwrap();
-                    let current_description = self
-                        .config
-                        .config
-                        .templates
-                        .get(&rename_key)
-                        .unwrap()
-                        .description
-                        .clone();
-                    self.input = if let Some(description) = current_description {
-                        InputField::new_with_content(description)
-                    } else {
-                        InputField::new()
-                    };
-                    self.mode = EditUiMode::Rename(rename_key);
-                }
+            Key::Char('x') if self.list.len() > 0 => {
+                let (delete_key, template) =
+                    Self::visible_templates(self.config, &self.filter)[self.list.highlight];
+                let delete_name = template.name.clone();
+                self.mode = EditUiMode::Delete(delete_key, delete_name);
+            }
+            // Positions in a filtered list are not those of the templates, so
+            // templates are only moved in the full list.
+            Key::Char('K') if self.filter.is_empty() && self.list.highlight > 0 => {
+                self.move_highlighted(self.list.highlight - 1);
+            }
+            Key::Char('J')
+                if self.filter.is_empty() && self.list.highlight + 1 < self.list.len() =>
+            {
+                self.move_highlighted(self.list.highlight + 1);
+            }
+            Key::Char('/') => {
+                self.input = InputField::new_with_content(self.filter.clone());
+                self.mode = EditUiMode::Filter;
+            }
+            Key::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.refresh_list();
+            }
+            Key::Char('u') if self.last_deleted.is_some() => self.undo_delete(),
+            Key::Char('r') if self.list.len() > 0 => {
+                self.start_input(EditField::Name);
+            }
+            Key::Char('e') if self.list.len() > 0 => {
+                self.start_input(EditField::Description);
+            }
+            Key::Char('l') if self.list.len() > 0 => {
+                self.start_input(EditField::License);
+            }
+            Key::Char('v') if self.list.len() > 0 => {
+                self.start_input(EditField::Version);
             }
             _ => {}
         }
@@ -122,6 +166,37 @@ impl<'conf> EditUi<'conf> {
         None
     }
 
+    /// Moves the highlighted template to the given (adjacent) position, and saves
+    /// the new order right away.
+    fn move_highlighted(&mut self, to: usize) {
+        let from = self.list.highlight;
+        self.config.config_mut().swap_order(from, to);
+        self.list.swap_entries(from, to);
+        self.list.highlight = to;
+        if let Err(err) = self.config.save() {
+            self.mode = EditUiMode::Error(err.to_string());
+        }
+    }
+
+    /// Switches to the input prompt for the given field of the highlighted template,
+    /// prefilling the prompt with the field's current value.
+    fn start_input(&mut self, field: EditField) {
+        let (edit_key, template) =
+            Self::visible_templates(self.config, &self.filter)[self.list.highlight];
+        let current_value = match field {
+            EditField::Name => Some(template.name.clone()),
+            EditField::Description => template.description.clone(),
+            EditField::License => template.license.clone(),
+            EditField::Version => template.template_version.clone(),
+        };
+        self.input = if let Some(value) = current_value {
+            InputField::new_with_content(value)
+        } else {
+            InputField::new()
+        };
+        self.mode = EditUiMode::Input(field, edit_key);
+    }
+
     fn delete_input(
         &mut self,
         key: Key,
@@ -129,26 +204,36 @@ impl<'conf> EditUi<'conf> {
     ) -> Option<crate::ui::UiStateReaction> {
         match key {
             Key::Char('y') => {
-                self.list.remove_entry(self.list.highlight);
-                let template = self.config.config.templates.get(template_key).unwrap();
+                let template = self.config.config().templates.get(template_key).unwrap();
                 let template_dir = template.path.clone(); // For use in error message.
-                if let Err(err) = self.config.delete_template(template_key) {
-                    match err {
-                        crate::config::DeleteTemplateError::NoTemplate(_) => panic!(
-                            "Tried to remove highlighted template, but config has no template of corresponding key."),
-                        crate::config::DeleteTemplateError::IoErr(err) => {
-                            let err_message = format!("There was an error deleting the template from disk. \
-                            You may need to manually delete the following folder:\n\
-                            {}\n\
-                            Error:\n\
-                            {}",
-                    template_dir.to_string_lossy(),
-                    err.to_string());
-                            self.mode = EditUiMode::Error(err_message);
-                        },
+                let template_name = template.name.clone(); // Likewise.
+                match self.config.trash_template(template_key) {
+                    Ok(trashed) => {
+                        self.last_deleted = Some(trashed);
+                        self.list.remove_entry(self.list.highlight);
+                        self.mode = EditUiMode::List;
+                    }
+                    Err(crate::config::DeleteTemplateError::NoTemplate) => panic!(
+                        "Tried to remove highlighted template, but config has no template of corresponding key."),
+                    Err(crate::config::DeleteTemplateError::SharedPath(others)) => {
+                        let err_message = format!("Refusing to delete '{}', as its directory is \
+                        also used by: {}\n\
+                        Deleting it would remove the files of those templates as well. \
+                        Please fix the configuration file first (see `boyl doctor`).",
+                template_name,
+                others.join(", "));
+                        self.mode = EditUiMode::Error(err_message);
+                    }
+                    Err(crate::config::DeleteTemplateError::IoErr(err)) => {
+                        let err_message = format!("There was an error moving the template to the trash. \
+                        The template has not been deleted. Its folder is:\n\
+                        {}\n\
+                        Error:\n\
+                        {}",
+                template_dir.to_string_lossy(),
+                err);
+                        self.mode = EditUiMode::Error(err_message);
                     }
-                } else {
-                    self.mode = EditUiMode::List;
                 }
             }
             _ => self.mode = EditUiMode::List,
@@ -157,31 +242,78 @@ impl<'conf> EditUi<'conf> {
         None
     }
 
-    fn rename_input(
+    fn field_input(
         &mut self,
         key: Key,
+        field: EditField,
         template_key: &TemplateKey,
     ) -> Option<crate::ui::UiStateReaction> {
         match key {
             Key::Left => self.input.caret_move_left(),
             Key::Right => self.input.caret_move_right(),
+            Key::Alt('b') => self.input.caret_move_word_left(),
+            Key::Alt('f') => self.input.caret_move_word_right(),
+            Key::Ctrl('u') => self.input.clear_to_start(),
+            Key::Ctrl('k') => self.input.clear_to_end(),
             Key::Backspace => self.input.backspace_char(),
             Key::Ctrl('c') => {
                 self.mode = EditUiMode::List;
             }
             Key::Char('\n') | Key::Char('\r') => {
-                let new_description = {
-                    let new_description = self.input.consume_input();
-                    if new_description.trim().is_empty() {
+                let new_value = {
+                    let new_value = self.input.consume_input();
+                    if new_value.trim().is_empty() {
                         None
                     } else {
-                        Some(new_description)
+                        Some(new_value)
                     }
                 };
-                let template = self.config.config.templates.get_mut(template_key).unwrap();
-                template.description = new_description;
+                if let EditField::Name = field {
+                    self.rename(template_key, new_value.as_deref().unwrap_or(""));
+                    return None;
+                }
+                let template = self
+                    .config
+                    .config_mut()
+                    .templates
+                    .get_mut(template_key)
+                    .unwrap();
+                let previous = match field {
+                    EditField::Name => unreachable!("Renames are handled above."),
+                    EditField::Description => template.description.clone(),
+                    EditField::License => template.license.clone(),
+                    EditField::Version => template.template_version.clone(),
+                };
+                match field {
+                    EditField::Name => unreachable!("Renames are handled above."),
+                    EditField::Description => {
+                        describe::set_description(template, new_value.as_deref().unwrap_or(""))
+                    }
+                    EditField::Version => {
+                        template.template_version = new_value.map(|v| v.trim().to_string())
+                    }
+                    EditField::License => match new_value {
+                        None => template.license = None,
+                        Some(spdx_id) => match license::find_license(&spdx_id) {
+                            Some(spdx_id) => template.license = Some(spdx_id.to_string()),
+                            None => {
+                                self.mode = EditUiMode::Error(format!(
+                                    "{} is not a known license. Available licenses are: {}",
+                                    spdx_id.trim(),
+                                    license::available_licenses()
+                                ));
+                                return None;
+                            }
+                        },
+                    },
+                }
+                self.field_edits.push(FieldEdit {
+                    key: *template_key,
+                    field,
+                    previous,
+                });
                 self.list
-                    .replace_entry(self.list.highlight, Self::make_template_entry(&template));
+                    .replace_entry(self.list.highlight, ui::template::template_entry(template));
                 self.mode = EditUiMode::List;
             }
             Key::Char(c) => self.input.add_char(c),
@@ -190,18 +322,125 @@ impl<'conf> EditUi<'conf> {
         None
     }
 
+    /// Renames the template, refreshing the list (where the template may have
+    /// moved) on success, or showing the error otherwise.
+    fn rename(&mut self, template_key: &TemplateKey, new_name: &str) {
+        match self.config.rename_template(template_key, new_name) {
+            Ok(()) => {
+                let new_key = Config::get_template_key(new_name.trim());
+                for field_edit in self.field_edits.iter_mut() {
+                    if field_edit.key == *template_key {
+                        field_edit.key = new_key;
+                    }
+                }
+                self.refresh_list();
+                // The renamed template may have moved, or no longer match the filter.
+                if let Some(position) = Self::visible_templates(self.config, &self.filter)
+                    .iter()
+                    .position(|(key, _)| *key == new_key)
+                {
+                    self.list.highlight = position;
+                }
+                self.mode = EditUiMode::List;
+            }
+            Err(err) => self.mode = EditUiMode::Error(err.to_string()),
+        }
+    }
+
+    /// Undoes the changes to descriptions, licenses and versions made in this
+    /// session, latest first.
+    fn discard_field_edits(&mut self) {
+        let templates = &mut self.config.config_mut().templates;
+        for field_edit in self.field_edits.drain(..).rev() {
+            // The template may have been deleted since.
+            if let Some(template) = templates.get_mut(&field_edit.key) {
+                match field_edit.field {
+                    EditField::Name => unreachable!("Renames are not field edits."),
+                    EditField::Description => template.description = field_edit.previous,
+                    EditField::License => template.license = field_edit.previous,
+                    EditField::Version => template.template_version = field_edit.previous,
+                }
+            }
+        }
+    }
+
+    /// Restores the last deleted template, highlighting it if it is listed.
+    fn undo_delete(&mut self) {
+        let trashed = self.last_deleted.as_ref().unwrap();
+        match self.config.restore_template(trashed) {
+            Ok(()) => {
+                let restored_key = trashed.key;
+                self.last_deleted = None;
+                self.refresh_list();
+                if let Some(position) = Self::visible_templates(self.config, &self.filter)
+                    .iter()
+                    .position(|(key, _)| *key == restored_key)
+                {
+                    self.list.highlight = position;
+                }
+            }
+            Err(err) => {
+                self.mode = EditUiMode::Error(format!(
+                    "Could not restore '{}': {}",
+                    trashed.template.name, err
+                ));
+            }
+        }
+    }
+
+    fn filter_input(&mut self, key: Key) -> Option<crate::ui::UiStateReaction> {
+        match key {
+            Key::Esc | Key::Ctrl('c') => {
+                self.input = InputField::new();
+                self.mode = EditUiMode::List;
+            }
+            Key::Char('\n') | Key::Char('\r') => {
+                self.mode = EditUiMode::List;
+                return None;
+            }
+            Key::Left => self.input.caret_move_left(),
+            Key::Right => self.input.caret_move_right(),
+            Key::Alt('b') => self.input.caret_move_word_left(),
+            Key::Alt('f') => self.input.caret_move_word_right(),
+            Key::Ctrl('u') => self.input.clear_to_start(),
+            Key::Ctrl('k') => self.input.clear_to_end(),
+            Key::Backspace => self.input.backspace_char(),
+            Key::Delete => self.input.delete_char(),
+            Key::Char(c) => self.input.add_char(c),
+            _ => return None,
+        }
+        self.filter = self.input.consume_input();
+        self.refresh_list();
+        None
+    }
+
     fn draw_help(&mut self, f: &mut tui::Frame<impl Backend>) -> Rect {
         let mut helps = vec![];
-        if !self.config.config.templates.is_empty() {
+        if !self.config.config().templates.is_empty() {
             helps.extend(vec![
                 ui::help::make_help_box("Up/K", "Move up in list"),
                 ui::help::make_help_box("Down/J", "Move down in list"),
+                ui::help::make_help_box("PgUp/PgDn", "Move a page"),
+                ui::help::make_help_box("Home/End", "First/Last entry"),
+                ui::help::make_help_box("Shift+J/K", "Move template"),
                 ui::help::make_help_box("X", "Delete template"),
+                ui::help::make_help_box("R", "Rename"),
                 ui::help::make_help_box("E", "Edit description"),
+                ui::help::make_help_box("L", "Edit license"),
+                ui::help::make_help_box("V", "Edit version"),
+                ui::help::make_help_box("/", "Filter"),
             ]);
         }
-        helps.push(ui::help::make_help_box("Enter/Q", "Exit"));
-        let (help_texts, help_boxes): (Vec<String>, Vec<VisualBox>) = helps.into_iter().unzip();
+        if self.last_deleted.is_some() {
+            helps.push(ui::help::make_help_box("U", "Undo delete"));
+        }
+        if !self.filter.is_empty() {
+            helps.push(ui::help::make_help_box("Esc", "Clear filter"));
+        }
+        helps.push(ui::help::make_help_box("Enter/Q", "Save and exit"));
+        helps.push(ui::help::make_help_box("Ctrl+C", "Discard edits"));
+        let (help_texts, help_boxes): (Vec<ui::help::HelpText>, Vec<VisualBox>) =
+            helps.into_iter().unzip();
         ui::help::draw_help(help_texts, help_boxes, f, f.size())
     }
 
@@ -237,10 +476,15 @@ impl<'conf> EditUi<'conf> {
         remaining
     }
 
-    fn draw_prompt(&mut self, f: &mut tui::Frame<impl Backend>) -> Rect {
+    fn draw_prompt(&mut self, f: &mut tui::Frame<impl Backend>, field: EditField) -> Rect {
         let size = f.size();
         let prompt_text = if size.width > 45 {
-            "New description: "
+            match field {
+                EditField::Name => "New name: ",
+                EditField::Description => "New description: ",
+                EditField::License => "New license (SPDX): ",
+                EditField::Version => "New version: ",
+            }
         } else {
             ":"
         };
@@ -248,7 +492,7 @@ impl<'conf> EditUi<'conf> {
     }
 }
 
-impl<'conf, B: Backend> UiState<B> for EditUi<'conf> {
+impl<'conf, B: Backend, C: ConfigStore + Send> UiState<B> for EditUi<'conf, C> {
     fn require_ticking(&self) -> Option<std::time::Duration> {
         None
     }
@@ -257,7 +501,8 @@ impl<'conf, B: Backend> UiState<B> for EditUi<'conf> {
         match self.mode {
             EditUiMode::List => self.list_input(key),
             EditUiMode::Delete(template_key, _) => self.delete_input(key, &template_key.clone()),
-            EditUiMode::Rename(template_key) => self.rename_input(key, &template_key),
+            EditUiMode::Input(field, template_key) => self.field_input(key, field, &template_key),
+            EditUiMode::Filter => self.filter_input(key),
             EditUiMode::Error(_) => {
                 self.mode = EditUiMode::List;
                 None
@@ -265,6 +510,19 @@ impl<'conf, B: Backend> UiState<B> for EditUi<'conf> {
         }
     }
 
+    fn on_paste(&mut self, text: String) -> Option<crate::ui::UiStateReaction> {
+        match self.mode {
+            EditUiMode::Input(..) => self.input.paste(&text),
+            EditUiMode::Filter => {
+                self.input.paste(&text);
+                self.filter = self.input.consume_input();
+                self.refresh_list();
+            }
+            _ => {}
+        }
+        None
+    }
+
     fn on_tick(&mut self) -> Option<crate::ui::UiStateReaction> {
         None
     }
@@ -273,17 +531,41 @@ impl<'conf, B: Backend> UiState<B> for EditUi<'conf> {
         let remaining = match &self.mode {
             EditUiMode::List => self.draw_help(f),
             EditUiMode::Delete(_key, name) => self.draw_delete(f, name),
-            EditUiMode::Rename(_) => self.draw_prompt(f),
+            EditUiMode::Input(field, _) => {
+                let field = *field;
+                self.draw_prompt(f, field)
+            }
             EditUiMode::Error(err_message) => self.draw_error(f, err_message),
+            EditUiMode::Filter => {
+                let size = f.size();
+                ui::input::draw_input(f, size, &mut self.input, "Filter: ")
+            }
+        };
+        let title = if self.filter.is_empty() {
+            "Templates:".to_string()
+        } else {
+            format!("Templates matching '{}':", self.filter)
         };
-        let block = Block::default().borders(Borders::ALL).title("Templates:");
+        let block = Block::default().borders(Borders::ALL).title(title);
         let block_inner = block.inner(remaining);
         f.render_widget(block, remaining);
         self.list.draw(f, block_inner);
     }
 }
 
-pub fn edit(config: &mut LoadedConfig) {
-    let mut list_ui = EditUi::new(config);
-    crate::ui::run_ui(&mut list_ui);
+/// Runs the interactive template editor, and saves the configuration once it is
+/// exited.
+///
+/// If it is aborted (with `Ctrl+C`), the changes to descriptions, licenses and
+/// versions are discarded. Deleting, restoring, renaming and reordering
+/// templates take effect on disk right away, so those are kept either way.
+pub fn edit(config: &mut (impl ConfigStore + Send)) {
+    {
+        let mut list_ui = EditUi::new(config);
+        crate::ui::run_ui(&mut list_ui);
+        if list_ui.aborted {
+            list_ui.discard_field_edits();
+        }
+    }
+    crate::config::write_config_or_fail(config);
 }
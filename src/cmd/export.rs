@@ -0,0 +1,81 @@
+use crate::{cmd::find_or_exit, config::ConfigStore, template::TemplateManifest, walkdir};
+use colored::Colorize;
+use flate2::{write::GzEncoder, Compression};
+use futures::StreamExt;
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+/// Name of the manifest file in an exported archive.
+pub const MANIFEST_NAME: &str = "manifest.json";
+/// Directory of an exported archive under which the template's files are stored.
+pub const FILES_DIR: &str = "files";
+
+/// Writes the template's manifest, and every file and directory in the template,
+/// to the given archive.
+fn write_archive(
+    out: &Path,
+    manifest: &TemplateManifest,
+    template_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tokio_runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+    let mut entries = tokio_runtime
+        .block_on(
+            walkdir::visit(template_path, false)
+                .map(|entry| entry.map(|entry| (entry.path, entry.metadata.is_dir())))
+                .collect::<Vec<_>>(),
+        )
+        .into_iter()
+        .collect::<Result<Vec<(PathBuf, bool)>, _>>()?;
+    // Sorting makes the archive reproducible, and puts directories before their contents.
+    entries.sort();
+
+    let encoder = GzEncoder::new(File::create(out)?, Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+
+    let manifest = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    archive.append_data(&mut header, MANIFEST_NAME, manifest.as_slice())?;
+
+    for (path, is_dir) in entries {
+        let name = Path::new(FILES_DIR).join(path.strip_prefix(template_path)?);
+        if is_dir {
+            archive.append_dir(&name, &path)?;
+        } else {
+            archive.append_path_with_name(&path, &name)?;
+        }
+    }
+
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
+
+pub fn export(config: &impl ConfigStore, template_name: &str, out: &Path) {
+    let (_, template) = find_or_exit(config, template_name);
+
+    let manifest = TemplateManifest {
+        name: template.name.clone(),
+        description: template.description.clone(),
+        license: template.license.clone(),
+        template_version: template.template_version.clone(),
+    };
+    if let Err(err) = write_archive(out, &manifest, &template.path) {
+        std::fs::remove_file(out).ok();
+        eprintln!(
+            "{}",
+            format!("Could not export {}, with error: {}", template.name, err).red()
+        );
+        std::process::exit(exitcode::IOERR);
+    }
+
+    eprintln!(
+        "{} {} {}",
+        template.name.bold(),
+        "was exported to".dimmed(),
+        out.to_string_lossy()
+    );
+}
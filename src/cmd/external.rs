@@ -0,0 +1,32 @@
+use crate::config::LoadedConfig;
+use colored::Colorize;
+
+/// Runs a user-defined subcommand, passing it the remaining arguments, and
+/// exits with the script's exit code.
+///
+/// The script is told where to find boyl's configuration through the
+/// `BOYL_CONFIG_DIR` and `BOYL_TEMPLATES_DIR` environment variables.
+pub fn external(config: &LoadedConfig, script: &str, args: &[String]) -> ! {
+    let script = match shellexpand::full(script) {
+        Ok(script) => script.into_owned(),
+        Err(err) => {
+            eprintln!("{}", format!("Could not expand {}: {}", script, err).red());
+            std::process::exit(exitcode::CONFIG);
+        }
+    };
+    let status = std::process::Command::new(&script)
+        .args(args)
+        .env("BOYL_CONFIG_DIR", &config.path)
+        .env(
+            "BOYL_TEMPLATES_DIR",
+            crate::config::template_dir_or_fail(config),
+        )
+        .status();
+    match status {
+        Ok(status) => std::process::exit(status.code().unwrap_or(exitcode::SOFTWARE)),
+        Err(err) => {
+            eprintln!("{}", format!("Could not run {}: {}", script, err).red());
+            std::process::exit(exitcode::UNAVAILABLE);
+        }
+    }
+}
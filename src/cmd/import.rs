@@ -0,0 +1,228 @@
+use crate::{
+    cmd::export::{FILES_DIR, MANIFEST_NAME},
+    config::{Config, ConfigStore},
+    template::{Template, TemplateManifest},
+    userbool::UserBool,
+};
+use colored::Colorize;
+use flate2::read::GzDecoder;
+use read_input::prelude::*;
+use std::{
+    fs::File,
+    io::Read,
+    path::{Component, Path},
+};
+use uuid::Uuid;
+
+/// Parses the manifest of an exported archive.
+///
+/// The template is stored in a directory of its name, so a name that is not a
+/// single path component (see [`Config::is_valid_template_name`]) is rejected.
+fn parse_manifest(json: &str) -> Result<TemplateManifest, Box<dyn std::error::Error>> {
+    let manifest = serde_json::from_str::<TemplateManifest>(json)?;
+    if !Config::is_valid_template_name(manifest.name.trim()) {
+        return Err(format!(
+            "The archive's template name, {}, is not a valid template name.",
+            manifest.name
+        )
+        .into());
+    }
+    Ok(manifest)
+}
+
+/// Unpacks the files of an exported archive into `dir`, returning the archive's
+/// manifest.
+///
+/// Entries with absolute paths or `..` components, as well as links, are rejected,
+/// so that nothing is written outside of `dir`. So is a manifest whose name would
+/// place the template outside of the templates directory.
+pub fn unpack(
+    archive: impl Read,
+    dir: &Path,
+) -> Result<TemplateManifest, Box<dyn std::error::Error>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(archive));
+    let mut manifest = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(format!("The archive contains an unsafe path: {}", path.display()).into());
+        }
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            return Err(format!("The archive contains a link: {}", path.display()).into());
+        }
+
+        if path == Path::new(MANIFEST_NAME) {
+            let mut json = String::new();
+            entry.read_to_string(&mut json)?;
+            manifest = Some(parse_manifest(&json)?);
+        } else if let Ok(relative) = path.strip_prefix(FILES_DIR) {
+            let target = dir.join(relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            entry.unpack(&target)?;
+        }
+    }
+    manifest.ok_or_else(|| format!("The archive has no {}.", MANIFEST_NAME).into())
+}
+
+/// Reads the manifest of an exported archive, without unpacking anything.
+pub fn read_manifest(archive: impl Read) -> Result<TemplateManifest, Box<dyn std::error::Error>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(archive));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()? == Path::new(MANIFEST_NAME) {
+            let mut json = String::new();
+            entry.read_to_string(&mut json)?;
+            return parse_manifest(&json);
+        }
+    }
+    Err(format!("The archive has no {}.", MANIFEST_NAME).into())
+}
+
+pub fn import(config: &mut impl ConfigStore, archive: &Path) {
+    // The archive is unpacked into a staging directory, as the name of the
+    // template is only known once its manifest has been read.
+    let template_dir = crate::config::template_dir_or_fail(config);
+    let staging_dir = template_dir.join(format!(".import-{}", Uuid::new_v4()));
+    let manifest = match std::fs::create_dir(&staging_dir)
+        .and_then(|_| File::open(archive))
+        .map_err(|err| err.into())
+        .and_then(|file| unpack(file, &staging_dir))
+    {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            std::fs::remove_dir_all(&staging_dir).ok();
+            eprintln!(
+                "{}",
+                format!("Could not import {}: {}", archive.to_string_lossy(), err).red()
+            );
+            std::process::exit(exitcode::DATAERR);
+        }
+    };
+
+    let mut name = manifest.name.trim().to_string();
+    if config.config().is_name_or_alias(&name) {
+        eprintln!(
+            "{}",
+            format!("There is already a template or alias named {}.", name).red()
+        );
+        let rename = input::<UserBool>()
+            .repeat_msg(
+                format!(
+                    "Do you wish to import it under a different name? {} ",
+                    "[y/N]".dimmed()
+                )
+                .yellow(),
+            )
+            .default(false.into())
+            .get();
+        if !rename.value {
+            std::fs::remove_dir_all(&staging_dir).ok();
+            eprintln!("Aborting.");
+            std::process::exit(exitcode::USAGE);
+        }
+        name = loop {
+            let new_name = input::<String>().msg("New name: ".yellow()).get();
+            let new_name = new_name.trim();
+            if new_name.is_empty() {
+                eprintln!("{}", "A template name cannot be empty.".red());
+            } else if !Config::is_valid_template_name(new_name) {
+                eprintln!(
+                    "{}",
+                    "A template name cannot contain / or \\, nor be . or ..".red()
+                );
+            } else if config.config().is_name_or_alias(new_name) {
+                eprintln!("{}", "That name is taken as well.".red());
+            } else {
+                break new_name.to_string();
+            }
+        };
+    }
+
+    let target_dir = template_dir.join(&name);
+    let stored = if target_dir.exists() {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target_dir.to_string_lossy()),
+        ))
+    } else {
+        std::fs::rename(&staging_dir, &target_dir)
+    };
+    if let Err(err) = stored {
+        std::fs::remove_dir_all(&staging_dir).ok();
+        eprintln!(
+            "{}",
+            format!("Could not store the imported template: {}", err).red()
+        );
+        std::process::exit(exitcode::IOERR);
+    }
+
+    let template = Template {
+        name: name.clone(),
+        description: manifest.description,
+        path: target_dir,
+        license: manifest.license,
+        template_version: manifest.template_version,
+        order: config.config().next_order(),
+        created_at: Some(chrono::Local::now().to_rfc3339()),
+        ..Default::default()
+    };
+    config
+        .config_mut()
+        .templates
+        .insert(Config::get_template_key(&name), template);
+    eprintln!("{} {}", "Imported template".green(), name.bold());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+
+    /// An archive as written by `boyl export`, with the given manifest and a single
+    /// file.
+    fn archive(manifest: &str) -> Vec<u8> {
+        let mut archive = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+        for (path, contents) in [
+            (MANIFEST_NAME.to_string(), manifest),
+            (format!("{}/file.txt", FILES_DIR), "contents"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            archive
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+        archive.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn manifest_names_that_are_paths_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["../../escape", "/tmp/escape", "a/b", "a\\\\b", "..", " "] {
+            let archive = archive(&format!(r#"{{ "name": "{}", "description": null }}"#, name));
+            assert!(
+                read_manifest(archive.as_slice()).is_err(),
+                "{} was read",
+                name
+            );
+            assert!(
+                unpack(archive.as_slice(), dir.path()).is_err(),
+                "{} was unpacked",
+                name
+            );
+        }
+
+        let archive = archive(r#"{ "name": "fine", "description": null }"#);
+        let manifest = unpack(archive.as_slice(), dir.path()).unwrap();
+        assert_eq!(manifest.name, "fine");
+        assert!(dir.path().join("file.txt").is_file());
+    }
+}
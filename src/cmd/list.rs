@@ -1,15 +1,392 @@
-use crate::config::LoadedConfig;
+use crate::config::ConfigStore;
+use crate::template::Template;
 use colored::Colorize;
+use std::{path::Path, str::FromStr};
 
-pub fn list(config: &LoadedConfig) {
-    for template in config.config.templates.values() {
+/// How `list` orders the templates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortOrder {
+    /// The order set in `boyl edit`.
+    #[default]
+    Custom,
+    /// Alphabetically, by name.
+    Name,
+    /// Most recently used first; templates never used come last.
+    Recent,
+    /// Most used first.
+    Popular,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "custom" => Ok(SortOrder::Custom),
+            "name" => Ok(SortOrder::Name),
+            "recent" => Ok(SortOrder::Recent),
+            "popular" => Ok(SortOrder::Popular),
+            _ => Err(format!(
+                "Cannot sort by {}; expected custom, name, recent or popular.",
+                s
+            )),
+        }
+    }
+}
+
+impl SortOrder {
+    /// Sorts `templates`, which should be in their custom order, so that ties
+    /// are broken by it.
+    fn sort<K>(self, templates: &mut [(K, &Template)]) {
+        match self {
+            SortOrder::Custom => {}
+            SortOrder::Name => templates.sort_by_key(|(_, template)| template.name.to_lowercase()),
+            SortOrder::Recent => templates.sort_by_key(|(_, template)| {
+                // `None` sorts before `Some`, so the order is reversed.
+                std::cmp::Reverse(
+                    template
+                        .last_used_at
+                        .as_deref()
+                        .and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok()),
+                )
+            }),
+            SortOrder::Popular => {
+                templates.sort_by_key(|(_, template)| std::cmp::Reverse(template.use_count))
+            }
+        }
+    }
+}
+
+/// How `list` shows when a template was created and last used.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum TimeFormat {
+    /// Relative to now (e.g. `3 days ago`).
+    #[default]
+    Relative,
+    /// As ISO 8601 (e.g. `2021-05-04T13:02:11+01:00`).
+    Iso,
+    /// With a `strftime`-like format string (e.g. `%d/%m/%Y`).
+    Custom(String),
+}
+
+impl FromStr for TimeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "relative" => Ok(TimeFormat::Relative),
+            "iso" => Ok(TimeFormat::Iso),
+            // Anything else should be a format string, which, to tell it from a
+            // misspelling of the above, has to have a specifier.
+            _ if s.contains('%') => {
+                let invalid = chrono::format::StrftimeItems::new(s)
+                    .any(|item| item == chrono::format::Item::Error);
+                if invalid {
+                    Err(format!("{} is not a valid time format string.", s))
+                } else {
+                    Ok(TimeFormat::Custom(s.to_string()))
+                }
+            }
+            _ => Err(format!(
+                "Cannot show times as {}; expected relative, iso, or a format string like %Y-%m-%d.",
+                s
+            )),
+        }
+    }
+}
+
+impl TimeFormat {
+    /// Formats `time`, which is taken to be relative to `now` if the format is
+    /// relative.
+    pub fn format(
+        &self,
+        time: &chrono::DateTime<chrono::Local>,
+        now: &chrono::DateTime<chrono::Local>,
+    ) -> String {
+        match self {
+            TimeFormat::Relative => relative_time(*now - *time),
+            TimeFormat::Iso => time.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            TimeFormat::Custom(format) => time.format(format).to_string(),
+        }
+    }
+
+    /// Formats a timestamp as stored in the configuration (RFC 3339), which is
+    /// shown as is if it cannot be parsed.
+    fn format_stored(&self, time: &str, now: &chrono::DateTime<chrono::Local>) -> String {
+        match chrono::DateTime::parse_from_rfc3339(time) {
+            Ok(parsed) => self.format(&parsed.with_timezone(&chrono::Local), now),
+            Err(_) => time.to_string(),
+        }
+    }
+}
+
+/// Describes how long ago something happened (or, for a negative `elapsed`, how
+/// far in the future it is), in the largest whole unit, like `2 hours ago`.
+fn relative_time(elapsed: chrono::Duration) -> String {
+    const UNITS: [(&str, i64); 6] = [
+        ("year", 365 * 24 * 60 * 60),
+        ("month", 30 * 24 * 60 * 60),
+        ("week", 7 * 24 * 60 * 60),
+        ("day", 24 * 60 * 60),
+        ("hour", 60 * 60),
+        ("minute", 60),
+    ];
+    let seconds = elapsed.num_seconds();
+    let (name, length) = match UNITS.iter().find(|(_, length)| seconds.abs() >= *length) {
+        Some(unit) => *unit,
+        None => return "just now".to_string(),
+    };
+    let count = seconds.abs() / length;
+    let plural = if count == 1 { "" } else { "s" };
+    if seconds > 0 {
+        format!("{} {}{} ago", count, name, plural)
+    } else {
+        format!("in {} {}{}", count, name, plural)
+    }
+}
+
+/// JSON description of a template, as printed by `list --json`.
+#[derive(Serialize)]
+struct ListedTemplate<'t> {
+    name: &'t str,
+    description: Option<&'t str>,
+    path: &'t Path,
+    tags: &'t [String],
+    /// Number of files in the template, with `--stats`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<usize>,
+    /// Total size of the template's files in bytes, with `--stats`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    /// How many projects were created from the template, with `--stats`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uses: Option<u32>,
+}
+
+/// Counts the files under `dir` (recursively, without following links), and adds
+/// up their sizes. Subdirectories that cannot be read are skipped.
+fn directory_stats(dir: &Path) -> std::io::Result<(usize, u64)> {
+    let (mut files, mut size) = (0, 0);
+    for entry in dir.read_dir()?.flatten() {
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            if let Ok((dir_files, dir_size)) = directory_stats(&entry.path()) {
+                files += dir_files;
+                size += dir_size;
+            }
+        } else {
+            files += 1;
+            size += metadata.len();
+        }
+    }
+    Ok((files, size))
+}
+
+/// Formats a number of bytes with a binary unit (e.g. `3.4 KiB`).
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Lists the templates, or only those with the given tag (ignoring case), in the
+/// given order.
+///
+/// With `stats`, the files of every template are counted and measured, which
+/// requires walking their directories, and how often and when each was used is
+/// shown, with times formatted as `time_format`.
+pub fn list(
+    config: &impl ConfigStore,
+    json: bool,
+    tag: Option<&str>,
+    stats: bool,
+    sort: SortOrder,
+    time_format: &TimeFormat,
+) {
+    let mut templates = config
+        .config()
+        .ordered_templates()
+        .into_iter()
+        .filter(|(_, template)| match tag {
+            Some(tag) => template
+                .tags
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(tag.trim())),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+    sort.sort(&mut templates);
+
+    if json {
+        // The output is meant for other programs, so it is never colored.
+        colored::control::set_override(false);
+        let templates = templates
+            .into_iter()
+            .map(|(_, template)| {
+                let template_stats = if stats {
+                    directory_stats(&template.path).ok()
+                } else {
+                    None
+                };
+                ListedTemplate {
+                    name: &template.name,
+                    description: template.description.as_deref(),
+                    path: &template.path,
+                    tags: &template.tags,
+                    files: template_stats.map(|(files, _)| files),
+                    size: template_stats.map(|(_, size)| size),
+                    uses: if stats {
+                        Some(template.use_count)
+                    } else {
+                        None
+                    },
+                }
+            })
+            .collect::<Vec<ListedTemplate>>();
+        match serde_json::to_string(&templates) {
+            Ok(templates) => println!("{}", templates),
+            Err(err) => {
+                eprintln!("Could not serialize the templates, with error: {}", err);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+        return;
+    }
+
+    let now = chrono::Local::now();
+    for (_, template) in templates {
+        let version = template
+            .template_version
+            .as_ref()
+            .map(|version| format!(" [{}]", version).dimmed().to_string())
+            .unwrap_or_default();
+        let tags = template
+            .tags
+            .iter()
+            .map(|tag| format!(" #{}", tag).dimmed().to_string())
+            .collect::<String>();
         println!(
-            "{}\n  {}",
+            "{}{}\n  {}{}",
             template.name.bold(),
+            version,
             template
                 .description
                 .as_ref()
                 .unwrap_or(&"No description.".italic().to_string()),
+            tags,
+        );
+        if stats {
+            let summary = if !template.path.is_dir() {
+                "(directory missing)".to_string()
+            } else {
+                match directory_stats(&template.path) {
+                    Ok((1, size)) => format!("1 file, {}", human_size(size)),
+                    Ok((files, size)) => format!("{} files, {}", files, human_size(size)),
+                    Err(_) => "(directory unreadable)".to_string(),
+                }
+            };
+            println!("  {} {}", "Size:".dimmed(), summary);
+            println!(
+                "  {} {}",
+                "Uses:".dimmed(),
+                template.use_count.to_string().dimmed()
+            );
+            if let Some(created_at) = &template.created_at {
+                println!(
+                    "  {} {}",
+                    "Created:".dimmed(),
+                    time_format.format_stored(created_at, &now).dimmed()
+                );
+            }
+            let last_used = match &template.last_used_at {
+                Some(last_used_at) => time_format.format_stored(last_used_at, &now),
+                None => "never".to_string(),
+            };
+            println!("  {} {}", "Last used:".dimmed(), last_used.dimmed());
+        }
+        if !template.aliases.is_empty() {
+            println!("  {} {}", "Aliases:".dimmed(), template.aliases.join(", "));
+        }
+        if !template.includes.is_empty() {
+            println!(
+                "  {} {}",
+                "Includes:".dimmed(),
+                template.includes.join(", ")
+            );
+        }
+        if template.external {
+            println!("  {} {}", "From:".dimmed(), template.path.to_string_lossy());
+        }
+    }
+    for shadowed in &config.config().shadowed_templates {
+        eprintln!(
+            "{}",
+            format!(
+                "Ignoring {}, as there is already a template named {}.",
+                shadowed.path.to_string_lossy(),
+                shadowed.name
+            )
+            .yellow()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn relative_times_use_the_largest_whole_unit() {
+        assert_eq!(relative_time(Duration::seconds(0)), "just now");
+        assert_eq!(relative_time(Duration::seconds(59)), "just now");
+        assert_eq!(relative_time(Duration::seconds(60)), "1 minute ago");
+        assert_eq!(relative_time(Duration::minutes(150)), "2 hours ago");
+        assert_eq!(relative_time(Duration::days(3)), "3 days ago");
+        assert_eq!(relative_time(Duration::days(400)), "1 year ago");
+    }
+
+    #[test]
+    fn future_times_are_relative_too() {
+        assert_eq!(relative_time(Duration::seconds(-30)), "just now");
+        assert_eq!(relative_time(Duration::hours(-1)), "in 1 hour");
+        assert_eq!(relative_time(Duration::days(-14)), "in 2 weeks");
+    }
+
+    #[test]
+    fn time_formats_are_parsed() {
+        assert_eq!("relative".parse(), Ok(TimeFormat::Relative));
+        assert_eq!("ISO".parse(), Ok(TimeFormat::Iso));
+        assert_eq!(
+            "%d/%m/%Y".parse(),
+            Ok(TimeFormat::Custom("%d/%m/%Y".to_string()))
+        );
+        assert!("relatve".parse::<TimeFormat>().is_err());
+        assert!("%Q".parse::<TimeFormat>().is_err());
+    }
+
+    #[test]
+    fn custom_formats_are_applied() {
+        let time = chrono::Local
+            .with_ymd_and_hms(2021, 5, 4, 13, 2, 11)
+            .unwrap();
+        let format = TimeFormat::Custom("%d/%m/%Y %H:%M".to_string());
+        assert_eq!(format.format(&time, &time), "04/05/2021 13:02");
+        assert_eq!(
+            TimeFormat::Relative.format_stored("not a time", &time),
+            "not a time"
         );
     }
 }
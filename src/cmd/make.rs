@@ -1,52 +1,282 @@
 use crate::userbool::UserBool;
 use crate::{
-    config::{Config, LoadedConfig},
+    config::{Config, ConfigStore},
+    copy::{CopyError, CopyOptions},
+    gitignore, license,
     template::Template,
     ui::{self},
+    verbosity::Verbosity,
     walkdir,
 };
 use colored::Colorize;
 use futures::StreamExt;
 use parking_lot::RwLock;
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, fmt::Display, path::PathBuf, sync::Arc};
 
 use read_input::prelude::*;
 
-const ERR_NAME_TAKEN: &str = "There is already a template of that name.";
+pub enum MakeError {
+    EmptyName,
+    /// The name is not a single path component (see
+    /// [`Config::is_valid_template_name`]).
+    InvalidName,
+    /// There is already a template of the given name.
+    NameTaken,
+    /// There is already an alias of the given name.
+    NameIsAlias,
+    NoLocation,
+    NotADirectory,
+    UnreadableLocation(PathBuf, std::io::Error),
+    UnknownLicense(String),
+    /// A template to include does not exist.
+    UnknownInclude(String),
+    /// The template to take exclusion patterns from does not exist.
+    UnknownPatternsFrom(String),
+    /// The templates directory could not be created.
+    TemplateDir(std::io::Error),
+    /// The template's directory already exists, and there is no one to ask whether
+    /// to replace it.
+    TemplateDirExists(PathBuf),
+    /// The template's directory could not be removed or created.
+    IoErr(std::io::Error),
+    Copy(CopyError),
+    /// The user backed out, in the file picker or when asked to replace the
+    /// template's directory.
+    Aborted,
+}
+
+impl MakeError {
+    /// A suggestion of what to do about the error, if there is one.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            MakeError::UnknownLicense(_) => Some(format!(
+                "Available licenses are: {}",
+                license::available_licenses()
+            )),
+            MakeError::TemplateDirExists(path) => Some(format!(
+                "Remove it and try again: {}",
+                path.to_string_lossy()
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            MakeError::TemplateDir(_) => exitcode::OSERR,
+            MakeError::TemplateDirExists(_) => exitcode::CONFIG,
+            MakeError::IoErr(_) | MakeError::Copy(_) => exitcode::IOERR,
+            _ => exitcode::USAGE,
+        }
+    }
+}
+
+impl Display for MakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MakeError::EmptyName => write!(f, "A template name cannot be empty."),
+            MakeError::InvalidName => {
+                write!(f, "A template name cannot contain / or \\, nor be . or ..")
+            }
+            MakeError::NameTaken => write!(f, "There is already a template of that name."),
+            MakeError::NameIsAlias => write!(f, "There is already an alias of that name."),
+            MakeError::NoLocation => write!(f, "The template location does not exist."),
+            MakeError::NotADirectory => write!(f, "The template location is not a directory."),
+            MakeError::UnreadableLocation(path, e) => write!(
+                f,
+                "Could not read {}, with error: {}",
+                path.to_string_lossy(),
+                e
+            ),
+            MakeError::UnknownLicense(spdx_id) => write!(f, "{} is not a known license.", spdx_id),
+            MakeError::UnknownInclude(name) => write!(
+                f,
+                "Cannot include {}, as it is not an existing template.",
+                name
+            ),
+            MakeError::UnknownPatternsFrom(name) => {
+                write!(f, "{} is not an existing template.", name)
+            }
+            MakeError::TemplateDir(e) => {
+                write!(f, "Could not create the templates directory: {}", e)
+            }
+            MakeError::TemplateDirExists(_) => write!(
+                f,
+                "The template base directory already exists.\n\
+                This may be because you previously aborted the creation of a template of \
+                the same name."
+            ),
+            MakeError::IoErr(e) => write!(
+                f,
+                "Could not prepare the template base directory, with error: {}",
+                e
+            ),
+            MakeError::Copy(e) => write!(f, "{}", e),
+            MakeError::Aborted => write!(f, "Aborting."),
+        }
+    }
+}
+
+/// Optional settings for [`make`], as given in the command line.
+pub struct MakeOptions {
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub template_version: Option<String>,
+    /// Include every file, without launching the file picker.
+    pub all: bool,
+    /// Print the new template as JSON, instead of the usual output.
+    pub json: bool,
+    /// Names (or aliases) of templates to include in new projects.
+    pub includes: Vec<String>,
+    /// Exclude files that look binary.
+    pub exclude_binary: bool,
+    /// Name (or alias) of a template whose exclusion patterns to start with.
+    pub patterns_from: Option<String>,
+    /// Do not exclude the files ignored by a `.gitignore` at the template root.
+    pub no_gitignore: bool,
+    /// How many files to copy at once.
+    pub jobs: Option<usize>,
+    /// Copy what symbolic links point to, instead of the links themselves.
+    pub follow_symlinks: bool,
+    /// Keep the modification times of the copied files.
+    pub preserve: bool,
+    /// Stop (and remove the template) at the first file that cannot be copied.
+    pub strict: bool,
+    pub verbosity: Verbosity,
+    /// Print nothing but errors and warnings.
+    pub quiet: bool,
+    pub tags: Vec<String>,
+}
 
+/// JSON description of a template created by `make`, as printed with `--json`.
+#[derive(Serialize)]
+struct MadeTemplate<'t> {
+    #[serde(flatten)]
+    template: &'t Template,
+    /// Number of files copied into the template.
+    files: usize,
+    /// Total size of the copied files, in bytes.
+    size: u64,
+}
+
+/// Creates a new template, returning whether every file was copied into it.
+///
+/// A template missing some files is still registered, so that the files that
+/// were copied are kept.
 pub fn make(
-    config: &mut LoadedConfig,
+    config: &mut impl ConfigStore,
     template_name: String,
     template_dir: PathBuf,
-    template_description: Option<String>,
-    all: bool,
-) {
-    if config.config.templates.contains_key(&Config::get_template_key(&template_name)) {
-        println!("{}", ERR_NAME_TAKEN.red());
-        std::process::exit(exitcode::USAGE);
+    options: MakeOptions,
+) -> Result<bool, MakeError> {
+    let template_name = template_name.trim().to_string();
+    if template_name.is_empty() {
+        return Err(MakeError::EmptyName);
+    }
+    if !Config::is_valid_template_name(&template_name) {
+        return Err(MakeError::InvalidName);
+    }
+    if config
+        .config()
+        .templates
+        .contains_key(&Config::get_template_key(&template_name))
+    {
+        return Err(MakeError::NameTaken);
+    }
+    if config.config().find_alias(&template_name).is_some() {
+        return Err(MakeError::NameIsAlias);
     }
 
+    if !template_dir.exists() {
+        return Err(MakeError::NoLocation);
+    }
+    if !template_dir.is_dir() {
+        return Err(MakeError::NotADirectory);
+    }
+    if let Err(err) = template_dir.read_dir() {
+        return Err(MakeError::UnreadableLocation(template_dir, err));
+    }
+
+    let template_license = match options.license {
+        Some(spdx_id) => match license::find_license(&spdx_id) {
+            Some(spdx_id) => Some(spdx_id.to_string()),
+            None => return Err(MakeError::UnknownLicense(spdx_id)),
+        },
+        None => None,
+    };
+
+    let includes = options
+        .includes
+        .iter()
+        .map(|include| match config.config().find_template(include) {
+            Some((_, template)) => Ok(template.name.clone()),
+            None => Err(MakeError::UnknownInclude(include.clone())),
+        })
+        .collect::<Result<Vec<String>, MakeError>>()?;
+
+    let mut tags = vec![];
+    for tag in options.tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !tags.iter().any(|known: &String| known == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    let initial_patterns = match &options.patterns_from {
+        Some(other) => match config.config().find_template(other) {
+            Some((_, other)) => other.exclude_patterns.clone(),
+            None => return Err(MakeError::UnknownPatternsFrom(other.clone())),
+        },
+        None => vec![],
+    };
+
     let file_list = {
-        let mut ui_state = crate::ui::file::FilePickerUi::new(&template_dir);
-        if !all {
+        let mut ui_state =
+            crate::ui::file::FilePickerUi::with_patterns(&template_dir, &initial_patterns);
+        // A `.boylignore` is always applied, as it is written for boyl.
+        let mut ignore_files = vec![gitignore::BOYLIGNORE];
+        if !options.no_gitignore {
+            ignore_files.push(".gitignore");
+        }
+        for ignore_file in ignore_files {
+            for pattern in gitignore::read_patterns(&template_dir, ignore_file) {
+                let added = if pattern.negated {
+                    ui_state.file_list.except_pattern(&pattern.glob)
+                } else {
+                    ui_state.file_list.exclude_pattern(&pattern.glob)
+                };
+                // Lines that do not make a valid pattern are skipped.
+                added.ok();
+            }
+        }
+        if !options.all {
             ui::run_ui(&mut ui_state);
         }
 
         if ui_state.aborted {
-            std::process::exit(exitcode::USAGE);
+            return Err(MakeError::Aborted);
         }
         ui_state.file_list
     };
+    let exclude_patterns = file_list
+        .exclude_patterns()
+        .map(str::to_string)
+        .collect::<Vec<String>>();
 
     // We now copy the files to the templates directory, and store a new template in memory.
-    let target_base_dir = config.get_template_dir().join(&template_name);
+    let target_base_dir = config
+        .get_template_dir()
+        .map_err(MakeError::TemplateDir)?
+        .join(&template_name);
 
     if target_base_dir.exists() {
-        println!(
+        // There is no one to ask, when not run from a terminal.
+        if !termion::is_tty(&std::io::stdin()) {
+            return Err(MakeError::TemplateDirExists(target_base_dir));
+        }
+        eprintln!(
             "{}",
-            "The template base directory already exists.\n\
-        This may be because you previously aborted the creation of a template of \
-        the same name."
+            MakeError::TemplateDirExists(target_base_dir.clone())
+                .to_string()
                 .red()
         );
         let erase_and_continue = input::<UserBool>()
@@ -60,78 +290,137 @@ pub fn make(
             .default(false.into())
             .get();
 
-        match erase_and_continue.value {
-            true => {
-                std::fs::remove_dir_all(&target_base_dir)
-                    .expect("Could not remove the existing directory.");
-                std::fs::create_dir(&target_base_dir)
-                    .expect("Could not create template directory.");
-            }
-            false => {
-                println!("Aborting.");
-                std::process::exit(exitcode::CONFIG);
-            }
+        if !erase_and_continue.value {
+            return Err(MakeError::Aborted);
         }
+        std::fs::remove_dir_all(&target_base_dir).map_err(MakeError::IoErr)?;
     }
 
-    if let Err(err) = std::fs::create_dir(&target_base_dir) {
-        println!(
-            "Could not create the template base directory, with error: {}",
-            err
-        );
-        std::process::exit(exitcode::IOERR);
-    }
+    std::fs::create_dir(&target_base_dir).map_err(MakeError::IoErr)?;
 
     let tokio_runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
-    tokio_runtime.block_on({
+    let copy_stats = tokio_runtime.block_on({
         let base_path = template_dir.clone();
         let target_path = target_base_dir.clone();
         let files_list = Arc::new(file_list);
         let files_memo = Arc::new(RwLock::new(HashMap::<PathBuf, bool>::new()));
+        let exclude_binary = options.exclude_binary;
+        let follow_symlinks = options.follow_symlinks;
+        let copy_options = CopyOptions {
+            show_progress: !options.json && !options.quiet,
+            jobs: options.jobs.unwrap_or_else(crate::copy::default_jobs),
+            preserve_times: options.preserve,
+            strict: options.strict,
+            verbosity: options.verbosity,
+        };
+        let verbosity = options.verbosity;
         async move {
-            let files_to_include = Box::pin(walkdir::visit(&base_path).filter_map({
-                clone_move!(files_list);
-                clone_move!(files_memo);
-                move |x| {
+            let files_to_include =
+                Box::pin(walkdir::visit(&base_path, follow_symlinks).filter_map({
                     clone_move!(files_list);
                     clone_move!(files_memo);
-                    async move {
-                        match x {
-                            Ok(x) => {
-                                if files_list.is_included_memoized_async(&x.path(), files_memo) {
-                                    Some(x)
-                                } else {
+                    clone_move!(base_path);
+                    move |x| {
+                        clone_move!(files_list);
+                        clone_move!(files_memo);
+                        clone_move!(base_path);
+                        async move {
+                            match x {
+                                Ok(x) => {
+                                    let is_dir = x.metadata.is_dir();
+                                    let included = files_list
+                                        .is_included_memoized_async(&x.path, is_dir, files_memo);
+                                    let binary = included
+                                        && exclude_binary
+                                        && !is_dir
+                                        && crate::binary::is_probably_binary(&x.path);
+                                    if verbosity >= Verbosity::Very {
+                                        let relative = x.path.strip_prefix(&base_path).unwrap();
+                                        let reason = if binary {
+                                            "looks binary".to_string()
+                                        } else {
+                                            files_list.why_included(&x.path).to_string()
+                                        };
+                                        eprintln!(
+                                            "{} {} {}",
+                                            if included && !binary {
+                                                "Included"
+                                            } else {
+                                                "Excluded"
+                                            }
+                                            .dimmed(),
+                                            relative.to_string_lossy(),
+                                            format!("({})", reason).dimmed()
+                                        );
+                                    }
+                                    if included && !binary {
+                                        Some(x)
+                                    } else {
+                                        None
+                                    }
+                                }
+                                Err(e) => {
+                                    eprintln!("Ignoring file: {}", e);
                                     None
                                 }
                             }
-                            Err(e) => {
-                                println!("Ignoring file: {}", e);
-                                None
-                            }
                         }
                     }
-                }
-            }));
-            crate::copy::recursive_copy(&base_path, &target_path, files_to_include).await;
+                }));
+            crate::copy::recursive_copy(&base_path, &target_path, files_to_include, &copy_options)
+                .await
         }
     });
 
-    println!("New template {} was created.", template_name.bold());
-    println!(
-        "{} {} {}",
-        "Call".dimmed(),
-        format!("boyl new {}", template_name).green(),
-        "to create a new instance of this template.".dimmed()
-    );
+    let copy_stats = copy_stats.map_err(MakeError::Copy)?;
+    if !copy_stats.failures.is_empty() {
+        crate::copy::print_failures(&copy_stats.failures);
+    }
+    if !options.json && !options.quiet {
+        eprintln!("New template {} was created.", template_name.bold());
+        eprintln!(
+            "{} {} {}",
+            "Call".dimmed(),
+            format!("boyl new {}", template_name).green(),
+            "to create a new instance of this template.".dimmed()
+        );
+    }
 
     let new_template = Template {
         name: template_name,
-        description: template_description,
+        description: options.description,
         path: target_base_dir,
+        license: template_license,
+        aliases: vec![],
+        template_version: options.template_version,
+        includes,
+        order: config.config().next_order(),
+        substitutions: Default::default(),
+        variables: vec![],
+        exclude_patterns,
+        post_create: None,
+        external: false,
+        tags,
+        created_at: Some(chrono::Local::now().to_rfc3339()),
+        last_used_at: None,
+        use_count: 0,
     };
+    if options.json {
+        let made_template = MadeTemplate {
+            template: &new_template,
+            files: copy_stats.files,
+            size: copy_stats.bytes,
+        };
+        match serde_json::to_string(&made_template) {
+            Ok(made_template) => println!("{}", made_template),
+            Err(err) => eprintln!("Could not serialize the new template, with error: {}", err),
+        }
+    }
+
     let new_template_key = Config::get_template_key(&new_template.name);
     config
-        .config
+        .config_mut()
         .templates
         .insert(new_template_key, new_template);
+    Ok(copy_stats.failures.is_empty())
 }
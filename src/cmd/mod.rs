@@ -1,7 +1,45 @@
+use crate::{
+    config::{ConfigStore, TemplateKey},
+    template::Template,
+};
+use colored::Colorize;
+
+pub mod alias;
+pub mod config;
+pub mod describe;
+pub mod doctor;
+pub mod edit;
+pub mod export;
+pub mod external;
+pub mod import;
 pub mod list;
 pub mod make;
 pub mod new;
+pub mod open;
+pub mod rename;
 pub mod tree;
-pub mod edit;
+pub mod version;
 pub mod xoxo;
-pub mod version;
\ No newline at end of file
+
+/// Finds the template of the given name or alias, exiting if it does not exist.
+pub fn find_or_exit<'c>(
+    config: &'c impl ConfigStore,
+    template_name: &str,
+) -> (TemplateKey, &'c Template) {
+    match config.config().find_template(template_name) {
+        Some(x) => x,
+        None => {
+            eprintln!(
+                "{}",
+                format!("{} is not an existing template.", template_name).red()
+            );
+            eprintln!(
+                "{} {}{}",
+                "You can list existing templates with".dimmed(),
+                "boyl list".yellow(),
+                ".".dimmed()
+            );
+            std::process::exit(exitcode::USAGE);
+        }
+    }
+}
@@ -1,61 +1,1070 @@
 use crate::{
-    config::{Config, LoadedConfig},
-    userpath::UserDir,
+    config::{Config, ConfigStore, IncludeError, WriteConfigError},
+    copy::{CopyError, CopyOptions},
+    license,
+    template::{Template, TemplateManifest, TemplateVariable},
+    userbool::UserBool,
+    userpath::{self, UserPath},
+    verbosity::Verbosity,
     walkdir,
 };
+use chrono::Datelike;
 use colored::Colorize;
 use futures::StreamExt;
+use read_input::prelude::*;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+use tempfile::TempDir;
+use uuid::Uuid;
+
+/// Optional settings for [`new`], as given in the command line.
+pub struct NewOptions {
+    pub name: Option<String>,
+    pub location: Option<UserPath>,
+    /// Who to credit in a generated LICENSE file.
+    pub author: Option<String>,
+    /// Create the location directory, and any parents, if missing.
+    pub parents: bool,
+    /// Remove directories that end up empty after copying.
+    pub prune_empty: bool,
+    /// Patterns of files to mark as executable after copying.
+    pub set_executable: Vec<glob::Pattern>,
+    /// Only print what would be created.
+    pub dry_run: bool,
+    /// Create the project in the target directory even if it is not empty.
+    pub force: bool,
+    /// Replace existing files only with template files that are newer, without
+    /// asking. Implies `force`.
+    pub overwrite_if_newer: bool,
+    /// Do not run the templates' post-creation commands.
+    pub no_hooks: bool,
+    /// Environment variables to set for the post-creation commands.
+    pub hooks_env: Vec<(String, String)>,
+    /// URL of a git repository to use as the template.
+    pub from_git: Option<String>,
+    /// Use an archive read from standard input as the template.
+    pub from_stdin_archive: bool,
+    /// How many files to copy at once.
+    pub jobs: Option<usize>,
+    /// Copy what symbolic links point to, instead of the links themselves.
+    pub follow_symlinks: bool,
+    /// Keep the modification times of the copied files.
+    pub preserve: bool,
+    /// Stop (and remove what was copied) at the first file that cannot be copied.
+    pub strict: bool,
+    pub verbosity: Verbosity,
+    /// Print nothing but errors and warnings.
+    pub quiet: bool,
+    /// Print the absolute path of the new project, instead of the usual message.
+    pub print_path: bool,
+}
+
+pub enum NewError {
+    /// There is no template of the given name (or alias).
+    NoTemplate(String),
+    Include(IncludeError),
+    /// Neither a template, a repository, nor an archive were given.
+    NoSource,
+    /// The name would put the project outside of the location.
+    UnsafeName(String),
+    /// The name is empty, or only `.`, and so names no directory of its own.
+    EmptyName(String),
+    NoLocation(PathBuf),
+    LocationNotADirectory(PathBuf),
+    TargetNotADirectory(PathBuf),
+    /// The target has no parent or file name (e.g., it is the root), so it cannot
+    /// be staged.
+    InvalidTarget(PathBuf),
+    UnreadableTarget(PathBuf, std::io::Error),
+    /// The target is not empty, and `force` was not given.
+    TargetNotEmpty(PathBuf),
+    /// Standard input is a terminal, rather than an archive.
+    ArchiveNotPiped,
+    /// The archive could not be read or unpacked.
+    BadArchive(String),
+    /// The repository could not be cloned.
+    Clone(String),
+    /// A temporary or staging directory could not be created.
+    Staging(std::io::Error),
+    Copy(CopyError),
+    /// Files of the target would be overwritten, and there is no one to ask
+    /// whether to.
+    Conflicts(PathBuf),
+    /// The project could not be moved out of its staging directory.
+    Move(PathBuf, std::io::Error),
+    /// The template's use could not be recorded.
+    Config(WriteConfigError),
+    /// The user closed the template picker without choosing a template.
+    Aborted,
+}
+
+impl NewError {
+    /// A suggestion of what to do about the error, if there is one.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            NewError::NoTemplate(_) => Some(
+                "To list existing templates, call boyl list or create a new one with boyl make."
+                    .to_string(),
+            ),
+            NewError::NoLocation(_) => Some("Call with --parents to create it.".to_string()),
+            NewError::TargetNotEmpty(_) => {
+                Some("Call with --force to create the project in it anyway.".to_string())
+            }
+            NewError::ArchiveNotPiped => Some(
+                "Pipe an archive made with boyl export into boyl, as in \
+                curl <url> | boyl new --from-stdin-archive"
+                    .to_string(),
+            ),
+            NewError::Conflicts(_) => Some(
+                "Overwriting them with --force has to be confirmed from a terminal. Remove \
+                them first, run boyl from one, or call with --overwrite-if-newer to replace \
+                only the files that are older than the template's."
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
+
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            NewError::Include(_) | NewError::BadArchive(_) => exitcode::DATAERR,
+            NewError::Clone(_) => exitcode::UNAVAILABLE,
+            NewError::UnreadableTarget(..)
+            | NewError::Staging(_)
+            | NewError::Copy(_)
+            | NewError::Move(..)
+            | NewError::Config(_) => exitcode::IOERR,
+            _ => exitcode::USAGE,
+        }
+    }
+}
+
+impl Display for NewError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NewError::NoTemplate(name) => write!(f, "{} does not exist.", name),
+            NewError::Include(e) => write!(f, "Cannot create new template: {}", e),
+            NewError::NoSource => write!(
+                f,
+                "Give either the template to use, a repository with --from-git, or an \
+                archive with --from-stdin-archive."
+            ),
+            NewError::UnsafeName(name) => write!(
+                f,
+                "The name {} should be a relative path, and cannot contain '..'.",
+                name
+            ),
+            NewError::EmptyName(name) => write!(
+                f,
+                "The name '{}' does not name a directory for the project.",
+                name
+            ),
+            NewError::InvalidTarget(path) => write!(
+                f,
+                "{} cannot be a project directory.",
+                path.to_string_lossy()
+            ),
+            NewError::NoLocation(path) => write!(f, "{} does not exist.", path.to_string_lossy()),
+            NewError::LocationNotADirectory(path) => {
+                write!(f, "{} is not a directory.", path.to_string_lossy())
+            }
+            NewError::TargetNotADirectory(path) => write!(
+                f,
+                "{} already exists, and is not a directory.",
+                path.to_string_lossy()
+            ),
+            NewError::UnreadableTarget(path, e) => write!(
+                f,
+                "Could not read {}, with error: {}",
+                path.to_string_lossy(),
+                e
+            ),
+            NewError::TargetNotEmpty(path) => write!(
+                f,
+                "{} already exists, and is not empty.",
+                path.to_string_lossy()
+            ),
+            NewError::ArchiveNotPiped => write!(
+                f,
+                "--from-stdin-archive reads the template from standard input."
+            ),
+            NewError::BadArchive(e) => write!(f, "Could not read the archive: {}", e),
+            NewError::Clone(e) => write!(f, "{}", e),
+            NewError::Staging(e) => write!(
+                f,
+                "Could not create the target directory, with error: {}",
+                e
+            ),
+            NewError::Copy(e) => write!(f, "{}", e),
+            NewError::Conflicts(path) => write!(
+                f,
+                "Some files of the new project already exist in {}.",
+                path.to_string_lossy()
+            ),
+            NewError::Move(path, e) => write!(
+                f,
+                "Could not move the new project into {}, with error: {}",
+                path.to_string_lossy(),
+                e
+            ),
+            NewError::Config(e) => write!(f, "{}", e),
+            NewError::Aborted => write!(f, "Aborting."),
+        }
+    }
+}
 
-pub fn new(config: &LoadedConfig, template: &str, name: Option<&str>, location: Option<UserDir>) {
-    let location = location
+/// Creates a new project, recording (and saving) when and how often its template
+/// was used. Returns whether every file of the template was copied.
+pub fn new(
+    config: &mut impl ConfigStore,
+    template: Option<&str>,
+    options: NewOptions,
+) -> Result<bool, NewError> {
+    let parents = options.parents;
+    let location = options
+        .location
         .map(|d| d.path_buf)
-        .unwrap_or_else(|| std::env::current_dir().expect("Could not read current directory."));
-    let template_key = Config::get_template_key(template);
-    let template = match config.config.templates.get(&template_key) {
-        Some(template) => template,
-        None => {
-            println!("{}", format!("{} does not exist.", template).red());
-            println!(
-                "To list existing templates, call {} or create a new one with {}.",
-                "boyl list".yellow(),
-                "boyl make".yellow(),
+        .or_else(|| config.config().default_new_location.clone())
+        .unwrap_or_else(userpath::current_dir_or_exit);
+    // The archive is read whole right away, as its name is needed below, and
+    // standard input cannot be read twice.
+    let archive = if options.from_stdin_archive {
+        Some(read_stdin_archive()?)
+    } else {
+        None
+    };
+    let picked = match (template, &options.from_git, &archive) {
+        (None, None, None) => pick_template(config.config())?,
+        _ => None,
+    };
+    let template = template.or(picked.as_deref());
+    // A git repository is only cloned (and an archive only unpacked) once the
+    // target has been checked; until then, there are no layers to copy for it.
+    let (template_key, template_name, layers) = match (template, &options.from_git, &archive) {
+        (Some(template), None, None) => {
+            let (template_key, template) = config
+                .config()
+                .find_template(template)
+                .ok_or_else(|| NewError::NoTemplate(template.to_string()))?;
+            let layers = config
+                .config()
+                .resolve_includes(&template_key)
+                .map_err(NewError::Include)?;
+            (Some(template_key), template.name.clone(), layers)
+        }
+        (None, Some(url), None) => (None, repository_name(url), vec![]),
+        (None, None, Some((_, manifest))) => (None, manifest.name.trim().to_string(), vec![]),
+        _ => return Err(NewError::NoSource),
+    };
+    let name = options.name.as_deref().unwrap_or(&template_name);
+
+    // The name may contain path separators, to create the project in a nested
+    // directory, but it should not escape the location, nor be the location itself.
+    let mut named = false;
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(_) => named = true,
+            Component::CurDir => {}
+            _ => return Err(NewError::UnsafeName(name.to_string())),
+        }
+    }
+    if !named {
+        return Err(NewError::EmptyName(name.to_string()));
+    }
+
+    if !parents && !location.is_dir() {
+        if location.exists() {
+            return Err(NewError::LocationNotADirectory(location));
+        } else {
+            return Err(NewError::NoLocation(location));
+        }
+    }
+
+    // Normalizing the target (absolute, and without `.` components) ensures it
+    // has a proper parent and file name, which are needed to stage the project.
+    let absolute_location = if location.is_absolute() {
+        location.clone()
+    } else {
+        userpath::current_dir_or_exit().join(&location)
+    };
+    let target_base_dir = absolute_location
+        .join(name)
+        .components()
+        .collect::<PathBuf>();
+    if target_base_dir.exists() && !target_base_dir.is_dir() {
+        return Err(NewError::TargetNotADirectory(target_base_dir));
+    }
+    let target_has_entries = match target_base_dir.read_dir() {
+        Ok(mut entries) => entries.next().is_some(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => false,
+        Err(err) => return Err(NewError::UnreadableTarget(target_base_dir, err)),
+    };
+    if !options.force && !options.overwrite_if_newer && target_has_entries {
+        return Err(NewError::TargetNotEmpty(target_base_dir));
+    }
+
+    // Dropping this removes the temporary directory.
+    let cloned = match (archive, &options.from_git) {
+        (Some((archive, manifest)), _) => Some(unpack_archive(&archive, manifest)?),
+        (None, Some(url)) => Some(clone_repository(url, &template_name)?),
+        (None, None) => None,
+    };
+    let layers = cloned
+        .iter()
+        .map(|(_, template)| template)
+        .chain(layers)
+        .collect::<Vec<&Template>>();
+
+    if options.dry_run {
+        dry_run(&layers, &target_base_dir, options.follow_symlinks);
+        return Ok(true);
+    }
+
+    // Variables are asked for before anything is written, so that aborting the
+    // prompts leaves no trace.
+    let mut answers = BTreeMap::new();
+    for variable in layers.iter().flat_map(|layer| &layer.variables) {
+        if !answers.contains_key(&variable.key) {
+            answers.insert(variable.key.clone(), ask_variable(variable));
+        }
+    }
+
+    // The project is first built in a sibling staging directory, and only moved
+    // into place once complete, so that a failure does not leave a half-built
+    // project behind.
+    let (target_parent, target_file_name) =
+        match (target_base_dir.parent(), target_base_dir.file_name()) {
+            (Some(parent), Some(file_name)) => (parent, file_name),
+            _ => return Err(NewError::InvalidTarget(target_base_dir)),
+        };
+    let staging_dir = target_parent.join(format!(
+        ".{}.boyl-{}",
+        target_file_name.to_string_lossy(),
+        Uuid::new_v4()
+    ));
+    // The location is known to exist unless `parents` was given, so any missing
+    // parents at this point are part of the (nested) name.
+    std::fs::create_dir_all(target_parent)
+        .and_then(|_| std::fs::create_dir(&staging_dir))
+        .map_err(NewError::Staging)?;
+
+    let copy_options = CopyOptions {
+        show_progress: !options.quiet,
+        jobs: options.jobs.unwrap_or_else(crate::copy::default_jobs),
+        // The staged files are compared against the existing ones by their
+        // modification times, which have to be the template's for that.
+        preserve_times: options.preserve || options.overwrite_if_newer,
+        strict: options.strict,
+        verbosity: options.verbosity,
+    };
+    let verbosity = options.verbosity;
+    let follow_symlinks = options.follow_symlinks;
+    let tokio_runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+    let copy_failures = tokio_runtime.block_on({
+        async {
+            let mut failures = vec![];
+            // Each layer is copied over the previous ones, so the template's own
+            // files take precedence over those of the templates it includes.
+            for layer in &layers {
+                if verbosity >= Verbosity::Very {
+                    eprintln!(
+                        "{} {} {}",
+                        "Laying down".dimmed(),
+                        layer.name,
+                        format!("(from {})", layer.path.to_string_lossy()).dimmed()
+                    );
+                }
+                let files_to_include = Box::pin(
+                    walkdir::visit(&layer.path, follow_symlinks).filter_map(|x| async move {
+                        match x {
+                            Ok(x) => Some(x),
+                            Err(e) => {
+                                if verbosity >= Verbosity::Very {
+                                    eprintln!("Ignoring file: {}", e);
+                                }
+                                None
+                            }
+                        }
+                    }),
+                );
+                let stats = crate::copy::recursive_copy(
+                    &layer.path,
+                    &staging_dir,
+                    files_to_include,
+                    &copy_options,
+                )
+                .await?;
+                failures.extend(stats.failures);
+            }
+            Ok::<_, crate::copy::CopyError>(failures)
+        }
+    });
+    let copy_failures = copy_failures.map_err(NewError::Copy)?;
+
+    let now = chrono::Local::now();
+    let mut variables = BTreeMap::new();
+    variables.insert(
+        "name".to_string(),
+        target_base_dir
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned(),
+    );
+    variables.insert("date".to_string(), now.format("%Y-%m-%d").to_string());
+    variables.insert("year".to_string(), now.year().to_string());
+    for layer in &layers {
+        variables.extend(layer.substitutions.clone());
+    }
+    variables.extend(answers);
+    crate::substitute::substitute_tree(&staging_dir, &variables);
+
+    if options.prune_empty {
+        if let Err(err) = crate::copy::prune_empty_dirs(&staging_dir) {
+            eprintln!(
+                "{}",
+                format!("Could not remove empty directories, with error: {}", err).yellow()
+            );
+        }
+    }
+
+    if let Some(spdx_id) = layers.last().and_then(|template| template.license.as_ref()) {
+        stamp_license(&staging_dir, spdx_id, options.author.as_deref());
+    }
+
+    if !options.set_executable.is_empty() {
+        if cfg!(unix) {
+            if let Err(err) = crate::copy::set_executable(&staging_dir, &options.set_executable) {
+                eprintln!(
+                    "{}",
+                    format!("Could not mark files as executable, with error: {}", err).yellow()
+                );
+            }
+        } else {
+            eprintln!(
+                "{}",
+                "Ignoring --set-executable, as this platform has no executable permissions."
+                    .yellow()
             );
-            std::process::exit(exitcode::USAGE);
         }
+    }
+
+    // Existing files are only asked about once the project is staged, as their
+    // names may depend on the substitutions.
+    let conflicts = conflicts_with(&staging_dir, &target_base_dir);
+    // There is no one to ask, when not run from a terminal.
+    if conflicts && !options.overwrite_if_newer && !termion::is_tty(&std::io::stdin()) {
+        std::fs::remove_dir_all(&staging_dir).ok();
+        return Err(NewError::Conflicts(target_base_dir));
+    }
+    let overwrite = if options.overwrite_if_newer {
+        Overwrite::IfNewer
+    } else if conflicts
+        && input::<UserBool>()
+            .repeat_msg(
+                format!(
+                    "Some files of the new project already exist in {}. Overwrite them? {} ",
+                    target_base_dir.to_string_lossy(),
+                    "[y/N]".dimmed()
+                )
+                .yellow(),
+            )
+            .default(false.into())
+            .get()
+            .value
+    {
+        Overwrite::Always
+    } else {
+        Overwrite::Never
     };
-    let name = name.unwrap_or(&template.name);
+    let mut merge_stats = MergeStats::default();
+    if let Err(err) = move_into_place(&staging_dir, &target_base_dir, overwrite, &mut merge_stats) {
+        std::fs::remove_dir_all(&staging_dir).ok();
+        return Err(NewError::Move(target_base_dir, err));
+    }
 
-    let target_base_dir = location.join(name);
-    if target_base_dir.exists() && target_base_dir.read_dir().unwrap().next().is_some() {
-        println!("{}", "Cannot create new template:".red());
-        println!(
-            "{} already exists, and is not empty.",
+    if !options.quiet && !options.print_path {
+        eprintln!(
+            "{} {} {} {}.",
+            "Created new template".green(),
+            template_name,
+            "in".green(),
             target_base_dir.to_string_lossy()
         );
-        std::process::exit(exitcode::USAGE);
+        if options.overwrite_if_newer {
+            eprintln!(
+                "{}",
+                format!(
+                    "Updated {} existing files, skipped {} that were as new as the template's, and kept {} unrelated ones.",
+                    merge_stats.updated, merge_stats.skipped, merge_stats.kept
+                )
+                .dimmed()
+            );
+        }
     }
 
-    std::fs::create_dir(target_base_dir.clone()).expect("Could not create target base directory.");
+    if !options.no_hooks {
+        let hook_env = hook_environment(&variables, &options.hooks_env);
+        for command in layers.iter().filter_map(|layer| layer.post_create.as_ref()) {
+            run_hook(
+                &crate::substitute::substitute(command, &variables),
+                &target_base_dir,
+                &hook_env,
+                options.quiet,
+                options.print_path,
+            );
+        }
+    }
 
-    let tokio_runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
-    tokio_runtime.block_on({
-        async {
-            let files_to_include =
-                Box::pin(walkdir::visit(&template.path).filter_map(|x| async move {
-                    match x {
-                        Ok(x) => Some(x),
-                        Err(_) => None,
+    if let Some(template) = template_key.and_then(|key| config.config_mut().templates.get_mut(&key))
+    {
+        template.last_used_at = Some(chrono::Local::now().to_rfc3339());
+        template.use_count = template.use_count.saturating_add(1);
+        config.save().map_err(NewError::Config)?;
+    }
+
+    // The files that could be copied are kept, but the project is incomplete.
+    if !copy_failures.is_empty() {
+        crate::copy::print_failures(&copy_failures);
+        return Ok(false);
+    }
+    // Printed last, so that it follows any warnings about the copy or the hooks.
+    if options.print_path {
+        let path = target_base_dir.canonicalize().unwrap_or(target_base_dir);
+        println!("{}", path.to_string_lossy());
+    }
+    Ok(true)
+}
+
+/// Lets the template be chosen from a list, when none was given and there is a
+/// terminal to show the list in (and templates to show).
+fn pick_template(config: &Config) -> Result<Option<String>, NewError> {
+    let interactive = termion::is_tty(&std::io::stdin()) && termion::is_tty(&std::io::stdout());
+    if !interactive || config.templates.is_empty() {
+        return Ok(None);
+    }
+    let mut picker = crate::ui::template::TemplatePickerUi::new(config);
+    crate::ui::run_ui(&mut picker);
+    match picker.picked.and_then(|key| config.templates.get(&key)) {
+        Some(template) => Ok(Some(template.name.clone())),
+        None => Err(NewError::Aborted),
+    }
+}
+
+/// The environment variables set for post-creation commands, on top of boyl's own
+/// environment: every substitution variable, as `BOYL_VAR_<KEY>` (upper-cased, with
+/// characters other than letters and digits replaced by `_`), and then those given
+/// with `--hooks-env`, which win over the former if their names clash.
+fn hook_environment(
+    variables: &BTreeMap<String, String>,
+    hooks_env: &[(String, String)],
+) -> BTreeMap<String, String> {
+    let mut env = variables
+        .iter()
+        .map(|(key, value)| {
+            let key = key
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_uppercase()
+                    } else {
+                        '_'
                     }
-                }));
-            crate::copy::recursive_copy(&template.path, &target_base_dir, files_to_include).await;
+                })
+                .collect::<String>();
+            (format!("BOYL_VAR_{}", key), value.clone())
+        })
+        .collect::<BTreeMap<String, String>>();
+    env.extend(hooks_env.iter().cloned());
+    env
+}
+
+/// Runs a post-creation command in the new project, through the shell, with its
+/// output going to the terminal, and `env` added to its environment. With
+/// `keep_stdout_clean`, the command's standard output goes to standard error
+/// instead, so as not to mix with boyl's own.
+///
+/// The project has already been created at this point, so a failing command is
+/// only warned about. Unless `quiet` is set, the command is printed first.
+fn run_hook(
+    command: &str,
+    project_dir: &Path,
+    env: &BTreeMap<String, String>,
+    quiet: bool,
+    keep_stdout_clean: bool,
+) {
+    if !quiet {
+        eprintln!("{} {}", "Running".dimmed(), command);
+    }
+    let mut shell = if cfg!(windows) {
+        let mut shell = std::process::Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = std::process::Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    shell.current_dir(project_dir).envs(env);
+    if keep_stdout_clean {
+        shell.stdout(std::io::stderr());
+    }
+    let status = shell.status();
+    let warning = match status {
+        Ok(status) if status.success() => return,
+        Ok(status) => format!("{} exited with {}.", command, status),
+        Err(err) => format!("Could not run {}, with error: {}", command, err),
+    };
+    eprintln!("{}", warning.yellow());
+}
+
+/// A name for a project made from the repository at `url`, namely the last
+/// component of the URL, without `.git`.
+fn repository_name(url: &str) -> String {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or(url)
+        .trim_end_matches(".git");
+    if name.is_empty() {
+        "project".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Makes a shallow clone of the repository at `url` in a temporary directory, to
+/// be used as a template of the given name.
+///
+/// The temporary directory is removed when the returned `TempDir` is dropped.
+fn clone_repository(url: &str, name: &str) -> Result<(TempDir, Template), NewError> {
+    let checkout = tempfile::tempdir().map_err(NewError::Staging)?;
+    let path = checkout.path().join("repository");
+    let status = std::process::Command::new("git")
+        .args(["clone", "--depth", "1", "--quiet", "--", url])
+        .arg(&path)
+        .status();
+    let error = match status {
+        Ok(status) if status.success() => None,
+        Ok(_) => Some(format!("Could not clone {}.", url)),
+        Err(err) => Some(format!("Could not run git, with error: {}", err)),
+    };
+    if let Some(error) = error {
+        return Err(NewError::Clone(error));
+    }
+    // The history of the repository is not part of the template.
+    std::fs::remove_dir_all(path.join(".git")).ok();
+
+    let template = Template {
+        name: name.to_string(),
+        path,
+        ..Default::default()
+    };
+    Ok((checkout, template))
+}
+
+/// Reads an exported archive (see `boyl export`) from standard input, returning
+/// it along with its manifest. Standard input cannot be a terminal, as the
+/// archive is meant to be piped in.
+fn read_stdin_archive() -> Result<(Vec<u8>, TemplateManifest), NewError> {
+    if termion::is_tty(&std::io::stdin()) {
+        return Err(NewError::ArchiveNotPiped);
+    }
+    let mut archive = vec![];
+    let manifest = std::io::stdin()
+        .read_to_end(&mut archive)
+        .map_err(|err| err.into())
+        .and_then(|_| crate::cmd::import::read_manifest(archive.as_slice()))
+        .map_err(|err| NewError::BadArchive(err.to_string()))?;
+    Ok((archive, manifest))
+}
+
+/// Unpacks an archive read with [`read_stdin_archive`] into a temporary
+/// directory, to use as a template, in the same way as `boyl import` (and with
+/// the same guards against paths that would escape the directory).
+fn unpack_archive(
+    archive: &[u8],
+    manifest: TemplateManifest,
+) -> Result<(TempDir, Template), NewError> {
+    let unpacked = tempfile::tempdir().map_err(NewError::Staging)?;
+    let path = unpacked.path().join("template");
+    std::fs::create_dir(&path)
+        .map_err(|err| err.into())
+        .and_then(|_| crate::cmd::import::unpack(archive, &path))
+        .map_err(|err| NewError::BadArchive(err.to_string()))?;
+
+    let template = Template {
+        name: manifest.name.trim().to_string(),
+        description: manifest.description,
+        path,
+        license: manifest.license,
+        template_version: manifest.template_version,
+        ..Default::default()
+    };
+    Ok((unpacked, template))
+}
+
+/// Prints the paths that copying the given layers into `target_dir` would create,
+/// without touching the file system.
+fn dry_run(layers: &[&Template], target_dir: &Path, follow_symlinks: bool) {
+    let tokio_runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+    // Later layers overwrite the files of earlier ones, so paths are counted once.
+    let paths = tokio_runtime.block_on(async {
+        let mut paths = BTreeMap::<PathBuf, bool>::new();
+        for layer in layers {
+            let mut files = Box::pin(
+                walkdir::visit(&layer.path, follow_symlinks).filter_map(|x| async move { x.ok() }),
+            );
+            while let Some(entry) = files.next().await {
+                let relative = entry.path.strip_prefix(&layer.path).unwrap().to_path_buf();
+                paths.insert(relative, entry.metadata.is_dir());
+            }
         }
+        paths
     });
 
-    println!(
-        "{} {} {} {}.",
-        "Created new template".green(),
-        template.name,
-        "in".green(),
-        target_base_dir.to_string_lossy()
+    if target_dir.exists() {
+        println!(
+            "{} {}",
+            target_dir.to_string_lossy(),
+            "(already exists)".dimmed()
+        );
+    } else {
+        println!("{}", target_dir.to_string_lossy());
+    }
+    for path in paths.keys() {
+        println!("{}", target_dir.join(path).to_string_lossy());
+    }
+    let file_count = paths.values().filter(|is_dir| !**is_dir).count();
+    eprintln!(
+        "{}",
+        format!(
+            "Would create {} files; nothing was written (dry run).",
+            file_count
+        )
+        .dimmed()
     );
 }
+
+/// Asks the user for the value of a template variable.
+fn ask_variable(variable: &TemplateVariable) -> String {
+    let prompt = variable.prompt.as_deref().unwrap_or(&variable.key);
+    let message = match &variable.default {
+        Some(default) => format!("{} {} ", prompt, format!("[{}]", default).dimmed()),
+        None => format!("{} ", prompt),
+    };
+    let answer = input::<String>().msg(message.yellow()).get();
+    match (&variable.default, answer.trim().is_empty()) {
+        (Some(default), true) => default.clone(),
+        _ => answer,
+    }
+}
+
+/// Whether moving the staged project into `target_dir` would replace anything,
+/// i.e., whether a staged file exists in the target, or a staged directory exists
+/// in the target as something other than a directory.
+fn conflicts_with(staging_dir: &Path, target_dir: &Path) -> bool {
+    let entries = match staging_dir.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    entries.flatten().any(|entry| {
+        let target = target_dir.join(entry.file_name());
+        match (entry.path().is_dir(), target.is_dir()) {
+            (true, true) => conflicts_with(&entry.path(), &target),
+            _ => target.exists(),
+        }
+    })
+}
+
+/// What to do with the files of the target directory that the staged project
+/// also has.
+#[derive(Clone, Copy, PartialEq)]
+enum Overwrite {
+    Never,
+    Always,
+    /// Replace them only if the staged file was modified more recently.
+    IfNewer,
+}
+
+/// How the files of the target directory fared when the staged project was
+/// merged into it.
+#[derive(Default)]
+struct MergeStats {
+    /// Existing files that were replaced.
+    updated: usize,
+    /// Existing files that were kept, as the staged ones were not newer.
+    skipped: usize,
+    /// Existing files that the staged project does not have.
+    kept: usize,
+}
+
+/// Whether `staged` was modified more recently than `existing`.
+fn is_newer(staged: &Path, existing: &Path) -> std::io::Result<bool> {
+    let modified = |path: &Path| path.symlink_metadata().and_then(|meta| meta.modified());
+    Ok(modified(staged)? > modified(existing)?)
+}
+
+/// Moves the staged project to the target directory.
+///
+/// If the target already exists, the staged contents are merged into it instead,
+/// so that the directory itself is preserved (it may, for example, be the current
+/// directory of the shell), as are its unrelated files. Files that already exist
+/// in the target are replaced or kept according to `overwrite`, and counted in
+/// `stats`.
+fn move_into_place(
+    staging_dir: &Path,
+    target_dir: &Path,
+    overwrite: Overwrite,
+    stats: &mut MergeStats,
+) -> std::io::Result<()> {
+    if !target_dir.exists() {
+        return std::fs::rename(staging_dir, target_dir);
+    }
+    for entry in target_dir.read_dir()? {
+        if !staging_dir.join(entry?.file_name()).exists() {
+            stats.kept += 1;
+        }
+    }
+    for entry in staging_dir.read_dir()? {
+        let entry = entry?;
+        let target = target_dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() && target.is_dir() {
+            move_into_place(&entry.path(), &target, overwrite, stats)?;
+            continue;
+        }
+        if target.exists() {
+            let replace = match overwrite {
+                Overwrite::Never => false,
+                Overwrite::Always => true,
+                Overwrite::IfNewer => is_newer(&entry.path(), &target)?,
+            };
+            if !replace {
+                stats.skipped += 1;
+                continue;
+            }
+            stats.updated += 1;
+            if target.is_dir() {
+                std::fs::remove_dir_all(&target)?;
+            } else {
+                std::fs::remove_file(&target)?;
+            }
+        }
+        std::fs::rename(entry.path(), target)?;
+    }
+    // Kept files leave their staged counterparts behind.
+    std::fs::remove_dir_all(staging_dir)
+}
+
+/// Writes the text of the given license to a `LICENSE` file in `target_dir`,
+/// unless the directory already contains a license file.
+///
+/// Failing to write the license is not fatal, as the project has already been
+/// created at this point; a warning is printed instead.
+fn stamp_license(target_dir: &Path, spdx_id: &str, author: Option<&str>) {
+    if license::has_license_file(target_dir) {
+        return;
+    }
+    let author = author.map(str::to_string).unwrap_or_else(|| {
+        std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "the authors".to_string())
+    });
+    let year = chrono::Local::now().year();
+    let text = match license::render_license(spdx_id, year, &author) {
+        Some(text) => text,
+        None => {
+            eprintln!(
+                "{}",
+                format!("Not adding a license: {} is not a known license.", spdx_id).yellow()
+            );
+            return;
+        }
+    };
+    if let Err(err) = std::fs::write(target_dir.join("LICENSE"), text) {
+        eprintln!(
+            "{}",
+            format!("Could not write the LICENSE file, with error: {}", err).yellow()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cmd::make::{make, MakeOptions},
+        config::MemoryConfig,
+    };
+
+    /// The files under `dir`, by their path relative to it, with their contents.
+    fn read_tree(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+        let mut files = BTreeMap::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            for entry in current.read_dir().unwrap().flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    let contents = std::fs::read(&path).unwrap();
+                    files.insert(path.strip_prefix(dir).unwrap().to_path_buf(), contents);
+                }
+            }
+        }
+        files
+    }
+
+    fn make_options() -> MakeOptions {
+        MakeOptions {
+            description: None,
+            license: None,
+            template_version: None,
+            all: true,
+            json: false,
+            includes: vec![],
+            exclude_binary: false,
+            patterns_from: None,
+            no_gitignore: false,
+            jobs: Some(2),
+            follow_symlinks: false,
+            preserve: false,
+            strict: true,
+            verbosity: Verbosity::None,
+            quiet: true,
+            tags: vec![],
+        }
+    }
+
+    fn new_options(location: &Path, name: &str) -> NewOptions {
+        NewOptions {
+            name: Some(name.to_string()),
+            location: Some(UserPath {
+                path_buf: location.to_path_buf(),
+            }),
+            author: None,
+            parents: false,
+            prune_empty: false,
+            set_executable: vec![],
+            dry_run: false,
+            force: false,
+            overwrite_if_newer: false,
+            no_hooks: true,
+            hooks_env: vec![],
+            from_git: None,
+            from_stdin_archive: false,
+            jobs: Some(2),
+            follow_symlinks: false,
+            preserve: false,
+            strict: true,
+            verbosity: Verbosity::None,
+            quiet: true,
+            print_path: false,
+        }
+    }
+
+    #[test]
+    fn projects_match_the_files_their_template_was_made_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir_all(source.join("src/nested")).unwrap();
+        std::fs::create_dir_all(source.join("target/debug")).unwrap();
+        std::fs::write(source.join("README.md"), "# Readme\n").unwrap();
+        std::fs::write(source.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(source.join("src/nested/data.bin"), [0u8, 159, 146, 150]).unwrap();
+        std::fs::write(source.join("src/nested/debug.log"), "noise").unwrap();
+        std::fs::write(source.join("target/debug/app"), "build output").unwrap();
+        std::fs::write(source.join(".boylignore"), "*.log\ntarget\n").unwrap();
+
+        let mut store = MemoryConfig::new(dir.path().join("templates"));
+        let made = make(
+            &mut store,
+            "round-trip".to_string(),
+            source.clone(),
+            make_options(),
+        );
+        assert!(matches!(made, Ok(true)));
+
+        let projects = dir.path().join("projects");
+        std::fs::create_dir(&projects).unwrap();
+        let created = new(
+            &mut store,
+            Some("round-trip"),
+            new_options(&projects, "project"),
+        );
+        assert!(matches!(created, Ok(true)));
+
+        let mut expected = read_tree(&source);
+        expected.remove(Path::new("src/nested/debug.log"));
+        expected.remove(Path::new("target/debug/app"));
+        assert_eq!(read_tree(&projects.join("project")), expected);
+
+        let (_, template) = store.config.find_template("round-trip").unwrap();
+        assert_eq!(template.use_count, 1);
+        assert_eq!(store.saves.get(), 1);
+    }
+
+    #[test]
+    fn names_that_are_not_a_directory_of_the_location_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir(&source).unwrap();
+        std::fs::write(source.join("file.txt"), "template").unwrap();
+        let mut store = MemoryConfig::new(dir.path().join("templates"));
+        assert!(make(&mut store, "template".to_string(), source, make_options()).is_ok());
+
+        let projects = dir.path().join("projects");
+        std::fs::create_dir(&projects).unwrap();
+        for name in ["", ".", "./."] {
+            let created = new(&mut store, Some("template"), new_options(&projects, name));
+            assert!(matches!(created, Err(NewError::EmptyName(_))), "{}", name);
+        }
+        for name in ["..", "../project", "/project"] {
+            let created = new(&mut store, Some("template"), new_options(&projects, name));
+            assert!(matches!(created, Err(NewError::UnsafeName(_))), "{}", name);
+        }
+        let created = new(
+            &mut store,
+            Some("template"),
+            new_options(Path::new("/"), ""),
+        );
+        assert!(matches!(created, Err(NewError::EmptyName(_))));
+        assert_eq!(projects.read_dir().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn projects_are_not_created_in_non_empty_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir(&source).unwrap();
+        std::fs::write(source.join("file.txt"), "template").unwrap();
+        let mut store = MemoryConfig::new(dir.path().join("templates"));
+        assert!(make(&mut store, "template".to_string(), source, make_options()).is_ok());
+
+        let target = dir.path().join("project");
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("file.txt"), "mine").unwrap();
+        let created = new(
+            &mut store,
+            Some("template"),
+            new_options(dir.path(), "project"),
+        );
+        assert!(matches!(created, Err(NewError::TargetNotEmpty(_))));
+        assert_eq!(
+            std::fs::read_to_string(target.join("file.txt")).unwrap(),
+            "mine"
+        );
+
+        let created = new(
+            &mut store,
+            Some("missing"),
+            new_options(dir.path(), "other"),
+        );
+        assert!(matches!(created, Err(NewError::NoTemplate(_))));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{cmd::find_or_exit, config::ConfigStore};
+use colored::Colorize;
+use std::path::Path;
+
+/// Prints the directory of a template, so that it can be used as in
+/// `cd "$(boyl open foo)"`, or, if `editor` is set, opens it in `$VISUAL` or
+/// `$EDITOR` (whichever is set first). Without an editor to use, the directory
+/// is printed anyway.
+pub fn open(config: &impl ConfigStore, template_name: &str, editor: bool) {
+    let (_, template) = find_or_exit(config, template_name);
+
+    let editor_command = if editor {
+        std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .ok()
+            .filter(|command| !command.trim().is_empty())
+    } else {
+        None
+    };
+    match editor_command {
+        Some(command) => run_editor(&command, &template.path),
+        None => println!("{}", template.path.to_string_lossy()),
+    }
+}
+
+/// Runs the editor `command` (which may include arguments, as in `code -w`)
+/// on `path`, exiting if it cannot be run.
+fn run_editor(command: &str, path: &Path) {
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap();
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            eprintln!("{}", format!("The editor exited with {}.", status).yellow());
+        }
+        Err(err) => {
+            eprintln!(
+                "{}",
+                format!("Could not run the editor {}, with error: {}", program, err).red()
+            );
+            std::process::exit(exitcode::UNAVAILABLE);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use crate::{cmd::find_or_exit, config::ConfigStore};
+use colored::Colorize;
+
+pub fn rename(config: &mut impl ConfigStore, old_name: &str, new_name: &str) {
+    let (template_key, _) = find_or_exit(config, old_name);
+
+    match config.rename_template(&template_key, new_name) {
+        Ok(()) => eprintln!(
+            "{} {} {}",
+            old_name.bold(),
+            "is now called".dimmed(),
+            new_name.trim().bold()
+        ),
+        Err(err) => {
+            eprintln!("{}", err.to_string().red());
+            std::process::exit(exitcode::USAGE);
+        }
+    }
+}
@@ -1,25 +1,149 @@
-use crate::{config::{Config, LoadedConfig}, ui::{self, file::FileTreeUi}};
+use crate::{
+    cmd::find_or_exit,
+    config::ConfigStore,
+    ui::{
+        self,
+        file::{list, FileTreeUi},
+    },
+    walkdir,
+};
 use colored::Colorize;
+use futures::StreamExt;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
 
-pub fn tree(config: &LoadedConfig, template_name: &str) {
-    let template_key = Config::get_template_key(template_name);
-    let template = match config.config.templates.get(&template_key) {
-        Some(x) => x,
-        None => {
-            println!(
-                "{}",
-                format!("{} is not an existing template.", template_name).red()
+/// Shows the files of a template, interactively or (if `plain` is set) printed
+/// to stdout. If `depth` is given, no more than that many levels are shown.
+pub fn tree(
+    config: &impl ConfigStore,
+    template_name: &str,
+    only_text: bool,
+    plain: bool,
+    depth: Option<usize>,
+) {
+    let (_, template) = find_or_exit(config, template_name);
+    if depth == Some(0) {
+        eprintln!("{}", "The depth must be at least 1.".red());
+        std::process::exit(exitcode::USAGE);
+    }
+
+    if plain {
+        println!("{}", template.name);
+        print_tree(&template.path, "", only_text, depth);
+        return;
+    }
+    let mut ui_state = FileTreeUi::new(&template.path, only_text, depth);
+    ui::run_ui(&mut ui_state);
+}
+
+/// Prints the contents of `dir` as an ASCII tree, in the same order as the
+/// interactive tree, with every line starting with `prefix`. Symbolic links
+/// are shown with where they point to, and are not followed.
+///
+/// Only `depth` levels are printed, if given; directories whose contents are
+/// left out are marked with `…`.
+fn print_tree(dir: &Path, prefix: &str, only_text: bool, depth: Option<usize>) {
+    let children = list::read_children(dir, only_text).unwrap_or_default();
+    for (i, child) in children.iter().enumerate() {
+        let last = i == children.len() - 1;
+        let name = child.file_name().unwrap_or_default().to_string_lossy();
+        let connector = if last { "└── " } else { "├── " };
+        match std::fs::read_link(child) {
+            Ok(link_target) => println!(
+                "{}{}{} -> {}",
+                prefix,
+                connector,
+                name,
+                link_target.to_string_lossy()
+            ),
+            Err(_) if depth == Some(1) && list::is_real_dir(child) => {
+                println!("{}{}{} …", prefix, connector, name)
+            }
+            Err(_) => println!("{}{}{}", prefix, connector, name),
+        }
+        if list::is_real_dir(child) && depth != Some(1) {
+            let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            print_tree(
+                child,
+                &child_prefix,
+                only_text,
+                depth.map(|depth| depth - 1),
             );
-            println!(
-                "{} {}{}",
-                "You can list existing templates with".dimmed(),
-                "boyl list".yellow(),
-                ".".dimmed()
+        }
+    }
+}
+
+/// Prints the differences between the files of two templates, in a unified-diff
+/// like format: files only in `old_name` are prefixed with `-`, files only in
+/// `new_name` with `+`, and files in both with a space.
+///
+/// If `content` is set, a unified diff of each text file that is in both templates,
+/// but differs, follows.
+pub fn diff(config: &impl ConfigStore, old_name: &str, new_name: &str, content: bool) {
+    let (_, old) = find_or_exit(config, old_name);
+    let (_, new) = find_or_exit(config, new_name);
+
+    let tokio_runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+    let (old_files, new_files) = tokio_runtime
+        .block_on(async { (list_files(&old.path).await, list_files(&new.path).await) });
+
+    println!("--- {}", old.name);
+    println!("+++ {}", new.name);
+    for path in old_files.union(&new_files) {
+        let marker = match (old_files.contains(path), new_files.contains(path)) {
+            (true, false) => "-",
+            (false, true) => "+",
+            _ => " ",
+        };
+        println!("{} {}", marker, path.to_string_lossy());
+    }
+
+    if !content {
+        return;
+    }
+    for path in old_files.intersection(&new_files) {
+        let (old_path, new_path) = (old.path.join(path), new.path.join(path));
+        if old_path.is_dir()
+            || crate::binary::is_probably_binary(&old_path)
+            || crate::binary::is_probably_binary(&new_path)
+        {
+            continue;
+        }
+        let (old_text, new_text) = match (
+            std::fs::read_to_string(&old_path),
+            std::fs::read_to_string(&new_path),
+        ) {
+            (Ok(old_text), Ok(new_text)) => (old_text, new_text),
+            _ => {
+                eprintln!(
+                    "{}",
+                    format!("Could not read {}", path.to_string_lossy()).yellow()
+                );
+                continue;
+            }
+        };
+        if old_text != new_text {
+            let path = path.to_string_lossy();
+            print!(
+                "\n{}",
+                similar::TextDiff::from_lines(&old_text, &new_text)
+                    .unified_diff()
+                    .header(
+                        &format!("{}/{}", old.name, path),
+                        &format!("{}/{}", new.name, path)
+                    )
             );
-            std::process::exit(exitcode::USAGE);
         }
-    };
+    }
+}
 
-    let mut ui_state = FileTreeUi::new(&template.path);
-    ui::run_ui(&mut ui_state);
+/// The paths (relative to `base`) of the files and directories under `base`.
+async fn list_files(base: &Path) -> BTreeSet<PathBuf> {
+    walkdir::visit(base, false)
+        .filter_map(|entry| async move { entry.ok() })
+        .map(|entry| entry.path.strip_prefix(base).unwrap().to_path_buf())
+        .collect()
+        .await
 }
@@ -1,3 +1,42 @@
-pub fn version() {
+use crate::{config::ConfigStore, version::Version};
+use colored::Colorize;
+use std::cmp::Ordering;
+
+/// Prints the version of boyl, and, if `check` is set, how it compares to the
+/// version that wrote the configuration.
+pub fn version(config: &impl ConfigStore, check: bool) {
     println!("boyl v{}", crate::VERSION);
+    if !check {
+        return;
+    }
+
+    let written_by = match &config.config().written_by {
+        Some(written_by) => written_by,
+        None => {
+            println!("{}", "No configuration has been written yet.".dimmed());
+            return;
+        }
+    };
+    // A version that cannot be parsed predates versions being written at all.
+    let ordering = match Version::parse(written_by) {
+        Some(written_by) => written_by.cmp(&Version::current()),
+        None => Ordering::Less,
+    };
+    match ordering {
+        Ordering::Equal => println!("{}", "The configuration is up to date.".green()),
+        Ordering::Less => println!(
+            "{} {}",
+            format!("The configuration was written by boyl v{}.", written_by).yellow(),
+            "It is upgraded the next time it is saved.".dimmed()
+        ),
+        Ordering::Greater => println!(
+            "{} {}",
+            format!(
+                "The configuration was written by boyl v{}, which is newer than this one.",
+                written_by
+            )
+            .yellow(),
+            "Settings it added may be lost if this version saves it.".dimmed()
+        ),
+    }
 }
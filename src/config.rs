@@ -1,17 +1,17 @@
 use crate::template::Template;
+use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{hash_map::DefaultHasher, BTreeMap},
+    collections::{BTreeMap, BTreeSet},
     fmt::Display,
     fs,
-    hash::{Hash, Hasher},
     io::{BufReader, BufWriter},
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 /// Given the base configuration folder path, returns
 /// the path of the configuration JSON file.
-fn get_json_path(config_path: &Path) -> PathBuf {
+pub fn get_json_path(config_path: &Path) -> PathBuf {
     config_path.join("config.json")
 }
 
@@ -19,28 +19,66 @@ fn get_json_path(config_path: &Path) -> PathBuf {
 /// namely `(default config directory)/boyl`, where the default
 /// configuration directory is given by the `dirs` crate.
 ///
-/// As a side effect of this function, **if the default directory
+/// If the platform has no configuration directory (as may be the
+/// case in minimal environments), or it cannot be created, the
+/// first usable of `$HOME/.boyl` and `./.boyl` is used instead.
+/// If none of these can be used, `None` is returned.
+///
+/// As a side effect of this function, **if the chosen directory
 /// does not exist, it will be created**.
-pub fn default_config_dir() -> PathBuf {
-    let default_dir = dirs::config_dir()
-        .expect("`dirs` crate does not specify a config directory for this OS.")
-        .join("boyl");
-    if !default_dir.exists() {
-        std::fs::create_dir_all(default_dir.clone())
-            .expect("Failed to create the default configuration directory.");
-    }
-    default_dir
+pub fn default_config_dir() -> Option<PathBuf> {
+    let candidates = [
+        dirs::config_dir().map(|dir| dir.join("boyl")),
+        dirs::home_dir().map(|dir| dir.join(".boyl")),
+        std::env::current_dir().ok().map(|dir| dir.join(".boyl")),
+    ];
+    first_usable_dir(&candidates)
+}
+
+/// The first of the candidate directories that exists or can be created (which
+/// it then is). Missing candidates are skipped.
+fn first_usable_dir(candidates: &[Option<PathBuf>]) -> Option<PathBuf> {
+    candidates
+        .iter()
+        .flatten()
+        .find(|dir| dir.is_dir() || std::fs::create_dir_all(dir).is_ok())
+        .cloned()
 }
 
-pub fn write_config_or_fail(config: &LoadedConfig) {
-    if let Err(err) = config.write_config() {
-        println!("{}", err);
+/// Saves the configuration to disk or, failing that, prints the error and exits.
+pub fn write_config_or_fail(config: &impl ConfigStore) {
+    if let Err(err) = config.save() {
+        eprintln!("{}", err.to_string().red());
         std::process::exit(exitcode::IOERR);
     }
 }
 
+/// The directory where the files of new templates are stored or, failing to
+/// create it, prints the error and exits.
+pub fn template_dir_or_fail(config: &impl ConfigStore) -> PathBuf {
+    match config.get_template_dir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            eprintln!(
+                "{}",
+                format!(
+                    "Could not create the templates directory, with error: {}",
+                    err
+                )
+                .red()
+            );
+            std::process::exit(exitcode::OSERR);
+        }
+    }
+}
+
 pub type TemplateKey = u64;
 
+/// Canonicalizes a path if possible (i.e., if it exists), or returns it unchanged.
+fn canonical_or_same(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
 /// Configuration elements that persist between sessions;
 /// this struct is deserialized and serialized from/to a
 /// JSON file on program start/end.
@@ -55,23 +93,313 @@ pub type TemplateKey = u64;
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub version: String,
+    #[serde(serialize_with = "serialize_registered")]
     pub templates: BTreeMap<TemplateKey, Template>,
+    /// Named sets of defaults, selectable with `--profile` or `BOYL_PROFILE`.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// User-defined subcommands, mapping the name of the subcommand to the
+    /// script to run.
+    #[serde(default)]
+    pub commands: BTreeMap<String, String>,
+    /// Further directories whose subdirectories are templates, named after the
+    /// subdirectory. Relative paths are taken relative to the configuration
+    /// directory.
+    #[serde(default)]
+    pub extra_template_dirs: Vec<String>,
+    /// Where to create new projects when no location is given (nor set by the
+    /// selected profile), in place of the current directory.
+    #[serde(default)]
+    pub default_new_location: Option<PathBuf>,
+    /// Templates found in the extra template directories, but not loaded, as there
+    /// was already a template of their name.
+    #[serde(skip)]
+    pub shadowed_templates: Vec<Template>,
+    /// The version of boyl that wrote the configuration file, as found when it was
+    /// loaded (before `version` is updated by migrating it). `None` if there was no
+    /// configuration file.
+    #[serde(skip)]
+    pub written_by: Option<String>,
+}
+
+/// Serializes only the templates registered in the configuration, leaving out
+/// those found in the extra template directories.
+fn serialize_registered<S: serde::Serializer>(
+    templates: &BTreeMap<TemplateKey, Template>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(templates.iter().filter(|(_, template)| !template.external))
+}
+
+/// A named set of defaults, used in place of boyl's own defaults when the profile
+/// is selected. Explicit command line arguments still take precedence.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    /// Whether to color the output.
+    pub color: Option<bool>,
+    /// Who to credit in generated LICENSE files.
+    pub author: Option<String>,
+    /// Where to create new projects.
+    pub location: Option<String>,
+    /// How many files to copy at once, in `make` and `new`.
+    pub jobs: Option<usize>,
+    /// Where to store templates; relative paths are taken relative to the
+    /// configuration directory.
+    pub template_dir: Option<String>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
             templates: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+            commands: BTreeMap::new(),
+            extra_template_dirs: vec![],
+            default_new_location: None,
+            shadowed_templates: vec![],
+            written_by: None,
             version: super::VERSION.to_string(),
         }
     }
 }
 
 impl Config {
-    pub fn get_template_key(template_name: &str) -> u64 {
-        let mut hasher = DefaultHasher::default();
-        template_name.hash(&mut hasher);
-        hasher.finish()
+    /// The key of the template of the given name: the 64-bit FNV-1a hash of the
+    /// name's UTF-8 bytes.
+    ///
+    /// Keys are stored in the configuration file, so this must not change between
+    /// versions or platforms (as the standard library's `DefaultHasher` may).
+    pub fn get_template_key(template_name: &str) -> TemplateKey {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+        template_name.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    /// Whether `name` can be the name of a template. Templates are stored in a
+    /// directory of their name, under the templates directory, so the name must
+    /// be a single path component: no separators, `.`, `..` or root.
+    pub fn is_valid_template_name(name: &str) -> bool {
+        let mut components = Path::new(name).components();
+        !name.contains(['/', '\\'])
+            && matches!(
+                (components.next(), components.next()),
+                (Some(Component::Normal(_)), None)
+            )
+    }
+
+    /// Moves every template whose key is not that of its name (e.g., because it
+    /// was computed by an older version of boyl) to the right key.
+    ///
+    /// A template is left where it is if its key is taken.
+    fn rekey_templates(&mut self) {
+        let stale = self
+            .templates
+            .iter()
+            .map(|(key, template)| (*key, Config::get_template_key(&template.name)))
+            .filter(|(old_key, new_key)| old_key != new_key)
+            .collect::<Vec<(TemplateKey, TemplateKey)>>();
+        for (old_key, new_key) in stale {
+            if self.templates.contains_key(&new_key) {
+                continue;
+            }
+            let template = self.templates.remove(&old_key).unwrap();
+            self.templates.insert(new_key, template);
+        }
+    }
+
+    /// The templates, along with their keys, in the order they should be listed.
+    pub fn ordered_templates(&self) -> Vec<(TemplateKey, &Template)> {
+        let mut templates = self
+            .templates
+            .iter()
+            .map(|(key, template)| (*key, template))
+            .collect::<Vec<(TemplateKey, &Template)>>();
+        templates.sort_by_key(|(_, template)| template.order);
+        templates
+    }
+
+    /// The templates whose name or description contains `filter` (ignoring case),
+    /// in the order they should be listed.
+    pub fn templates_matching(&self, filter: &str) -> Vec<(TemplateKey, &Template)> {
+        let filter = filter.to_lowercase();
+        let matches = |text: &str| text.to_lowercase().contains(&filter);
+        self.ordered_templates()
+            .into_iter()
+            .filter(|(_, template)| {
+                matches(&template.name) || template.description.as_deref().is_some_and(matches)
+            })
+            .collect()
+    }
+
+    /// The `order` that places a new template after every existing one.
+    pub fn next_order(&self) -> u32 {
+        self.templates
+            .values()
+            .map(|template| template.order.saturating_add(1))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Swaps the positions of two templates, given by their index in
+    /// [`Config::ordered_templates`].
+    ///
+    /// As templates may share an `order` (e.g., if it was never set), every
+    /// template is first renumbered according to its current position.
+    pub fn swap_order(&mut self, a: usize, b: usize) {
+        let mut keys = self
+            .ordered_templates()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect::<Vec<TemplateKey>>();
+        keys.swap(a, b);
+        for (order, key) in keys.iter().enumerate() {
+            self.templates.get_mut(key).unwrap().order = order as u32;
+        }
+    }
+
+    /// Finds a template by its name or, if no template has that name, by one of
+    /// its aliases.
+    ///
+    /// Surrounding whitespace is ignored, both in `name_or_alias` and in the names
+    /// of the templates.
+    pub fn find_template(&self, name_or_alias: &str) -> Option<(TemplateKey, &Template)> {
+        let name_or_alias = name_or_alias.trim();
+        let key = Config::get_template_key(name_or_alias);
+        if let Some(template) = self.templates.get(&key) {
+            return Some((key, template));
+        }
+        // Templates made before names were trimmed may still have whitespace.
+        self.templates
+            .iter()
+            .find(|(_, template)| template.name.trim() == name_or_alias)
+            .or_else(|| self.find_alias(name_or_alias))
+            .map(|(key, template)| (*key, template))
+    }
+
+    /// Finds the template that has the given alias.
+    pub fn find_alias(&self, alias: &str) -> Option<(&TemplateKey, &Template)> {
+        let alias = alias.trim();
+        self.templates
+            .iter()
+            .find(|(_, template)| template.aliases.iter().any(|known| known == alias))
+    }
+
+    /// Whether `name` is taken, either by a template or as an alias.
+    pub fn is_name_or_alias(&self, name: &str) -> bool {
+        self.find_template(name).is_some()
+    }
+
+    /// Names of the templates that have leading or trailing whitespace.
+    pub fn untrimmed_names(&self) -> Vec<&str> {
+        self.templates
+            .values()
+            .map(|template| template.name.as_str())
+            .filter(|name| name.trim() != *name)
+            .collect()
+    }
+
+    /// Removes the leading and trailing whitespace of template names, moving the
+    /// templates to the key of their new name.
+    ///
+    /// Returns the names that could not be trimmed, because they would become
+    /// empty, or the name of another template.
+    pub fn trim_names(&mut self) -> Vec<String> {
+        let untrimmed = self
+            .templates
+            .iter()
+            .filter(|(_, template)| template.name.trim() != template.name)
+            .map(|(key, template)| (*key, template.name.clone()))
+            .collect::<Vec<(TemplateKey, String)>>();
+        let mut failed = vec![];
+        for (old_key, name) in untrimmed {
+            let trimmed = name.trim();
+            let new_key = Config::get_template_key(trimmed);
+            if trimmed.is_empty() || (new_key != old_key && self.templates.contains_key(&new_key)) {
+                failed.push(name);
+                continue;
+            }
+            let mut template = self.templates.remove(&old_key).unwrap();
+            template.name = trimmed.to_string();
+            self.templates.insert(new_key, template);
+        }
+        failed
+    }
+
+    /// Names of the templates, other than the template of the given key, that are
+    /// stored in the same directory as that template.
+    pub fn templates_sharing_path(&self, key: &TemplateKey) -> Vec<&str> {
+        let path = match self.templates.get(key) {
+            Some(template) => canonical_or_same(&template.path),
+            None => return vec![],
+        };
+        self.templates
+            .iter()
+            .filter(|(other_key, other)| {
+                *other_key != key && canonical_or_same(&other.path) == path
+            })
+            .map(|(_, other)| other.name.as_str())
+            .collect()
+    }
+
+    /// Groups of (two or more) templates that are stored in the same directory.
+    pub fn shared_paths(&self) -> BTreeMap<PathBuf, Vec<&str>> {
+        let mut by_path = BTreeMap::<PathBuf, Vec<&str>>::new();
+        for template in self.templates.values() {
+            by_path
+                .entry(canonical_or_same(&template.path))
+                .or_default()
+                .push(&template.name);
+        }
+        by_path.retain(|_, names| names.len() > 1);
+        by_path
+    }
+
+    /// The templates whose files make up a new instance of the template of the
+    /// given key: the templates it includes (recursively, and each only once), in
+    /// the order they should be laid down, followed by the template itself.
+    pub fn resolve_includes(&self, key: &TemplateKey) -> Result<Vec<&Template>, IncludeError> {
+        let mut order = vec![];
+        self.visit_includes(*key, &mut vec![], &mut BTreeSet::new(), &mut order)?;
+        Ok(order)
+    }
+
+    /// Depth-first step of [`Config::resolve_includes`]; `stack` holds the
+    /// templates being visited, so that cycles can be detected.
+    fn visit_includes<'c>(
+        &'c self,
+        key: TemplateKey,
+        stack: &mut Vec<TemplateKey>,
+        done: &mut BTreeSet<TemplateKey>,
+        order: &mut Vec<&'c Template>,
+    ) -> Result<(), IncludeError> {
+        if done.contains(&key) {
+            return Ok(());
+        }
+        let template = &self.templates[&key];
+        if let Some(start) = stack.iter().position(|visiting| *visiting == key) {
+            let mut cycle = stack[start..]
+                .iter()
+                .map(|visiting| self.templates[visiting].name.clone())
+                .collect::<Vec<String>>();
+            cycle.push(template.name.clone());
+            return Err(IncludeError::Cycle(cycle));
+        }
+        stack.push(key);
+        for include in &template.includes {
+            let (include_key, _) =
+                self.find_template(include)
+                    .ok_or_else(|| IncludeError::Missing {
+                        name: include.clone(),
+                        included_by: template.name.clone(),
+                    })?;
+            self.visit_includes(include_key, stack, done, order)?;
+        }
+        stack.pop();
+        done.insert(key);
+        order.push(template);
+        Ok(())
     }
 
     /// Deserialize a `Config` object from an in-disk `JSON` representation.
@@ -94,9 +422,24 @@ impl Config {
             Err(x) => return Err(LoadConfigError::FileError(x)),
         };
         let reader = BufReader::new(json_file);
-        serde_json::from_reader::<_, Config>(reader)
-            .map_err(|e| LoadConfigError::BadDeserialization(e, json_path.display().to_string()))
-            .map(Some)
+        let bad_deserialization =
+            |e| LoadConfigError::BadDeserialization(e, json_path.display().to_string());
+        let json =
+            serde_json::from_reader::<_, serde_json::Value>(reader).map_err(bad_deserialization)?;
+        let stored_version = json
+            .get("version")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let json = if stored_version == crate::VERSION {
+            json
+        } else {
+            crate::migrations::migrate(&stored_version, json)
+        };
+        let mut config = serde_json::from_value::<Config>(json).map_err(bad_deserialization)?;
+        config.written_by = Some(stored_version);
+        config.rekey_templates();
+        Ok(Some(config))
     }
 }
 
@@ -170,17 +513,261 @@ impl Display for WriteConfigError {
     }
 }
 
-pub enum DeleteTemplateError<'key> {
-    NoTemplate(&'key TemplateKey),
+pub enum IncludeError {
+    /// A template includes a template that does not exist.
+    Missing { name: String, included_by: String },
+    /// Templates include each other; the names form the cycle, starting and
+    /// ending on the same template.
+    Cycle(Vec<String>),
+}
+
+impl Display for IncludeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IncludeError::Missing { name, included_by } => write!(
+                f,
+                "{} includes {}, which is not an existing template.",
+                included_by, name
+            ),
+            IncludeError::Cycle(names) => write!(
+                f,
+                "Templates include each other in a cycle: {}",
+                names.join(" -> ")
+            ),
+        }
+    }
+}
+
+pub enum RenameTemplateError {
+    NoTemplate,
+    EmptyName,
+    /// The new name is not a single path component (see
+    /// [`Config::is_valid_template_name`]).
+    InvalidName,
+    /// There is already a template of the new name.
+    NameTaken,
+    /// There is already an alias of the new name.
+    NameIsAlias,
+    /// The template's directory could not be moved to match the new name.
+    IoErr(std::io::Error),
+}
+
+impl Display for RenameTemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenameTemplateError::NoTemplate => write!(f, "There is no such template."),
+            RenameTemplateError::EmptyName => write!(f, "A template name cannot be empty."),
+            RenameTemplateError::InvalidName => {
+                write!(f, "A template name cannot contain / or \\, nor be . or ..")
+            }
+            RenameTemplateError::NameTaken => {
+                write!(f, "There is already a template of that name.")
+            }
+            RenameTemplateError::NameIsAlias => {
+                write!(f, "There is already an alias of that name.")
+            }
+            RenameTemplateError::IoErr(e) => {
+                write!(f, "Could not rename the template's directory: {}", e)
+            }
+        }
+    }
+}
+
+pub enum DeleteTemplateError {
+    NoTemplate,
+    /// The template's directory is shared with the templates of the given names.
+    SharedPath(Vec<String>),
+    IoErr(std::io::Error),
+}
+
+pub enum RestoreTemplateError {
+    /// There is already a template of the restored template's name.
+    NameTaken,
+    /// Something already exists where the template's directory was.
+    PathTaken,
     IoErr(std::io::Error),
 }
 
+impl Display for RestoreTemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestoreTemplateError::NameTaken => {
+                write!(f, "There is already a template of the same name.")
+            }
+            RestoreTemplateError::PathTaken => {
+                write!(f, "The template's directory has since been taken.")
+            }
+            RestoreTemplateError::IoErr(e) => {
+                write!(f, "Could not move the template's directory back: {}", e)
+            }
+        }
+    }
+}
+
+/// Where the configuration is kept, as seen by the commands.
+///
+/// Commands read and modify the configuration through this trait, rather than
+/// through [`LoadedConfig`] directly, so that they do not depend on the
+/// configuration living in a file.
+pub trait ConfigStore {
+    /// The in-memory configuration.
+    fn config(&self) -> &Config;
+
+    fn config_mut(&mut self) -> &mut Config;
+
+    /// The directory where the files of new templates are stored, which is
+    /// created if it does not exist.
+    fn get_template_dir(&self) -> std::io::Result<PathBuf>;
+
+    /// Persists the in-memory configuration.
+    fn save(&self) -> Result<(), WriteConfigError>;
+
+    /// Renames a template, moving it to the key of its new name, and renaming its
+    /// directory if it is stored in the templates directory (and not shared with
+    /// other templates). Aliases and includes of the template are kept pointing
+    /// to it.
+    fn rename_template(
+        &mut self,
+        key: &TemplateKey,
+        new_name: &str,
+    ) -> Result<(), RenameTemplateError> {
+        let new_name = new_name.trim();
+        let new_key = Config::get_template_key(new_name);
+        let template_dir = self
+            .get_template_dir()
+            .map_err(RenameTemplateError::IoErr)?;
+        let config = self.config_mut();
+        let old_name = match config.templates.get(key) {
+            Some(template) => template.name.clone(),
+            None => return Err(RenameTemplateError::NoTemplate),
+        };
+        if new_name.is_empty() {
+            return Err(RenameTemplateError::EmptyName);
+        }
+        if !Config::is_valid_template_name(new_name) {
+            return Err(RenameTemplateError::InvalidName);
+        }
+        if new_key != *key && config.templates.contains_key(&new_key) {
+            return Err(RenameTemplateError::NameTaken);
+        }
+        if config
+            .find_alias(new_name)
+            .is_some_and(|(alias_key, _)| alias_key != key)
+        {
+            return Err(RenameTemplateError::NameIsAlias);
+        }
+
+        let old_path = config.templates[key].path.clone();
+        let new_path = template_dir.join(new_name);
+        if old_path.parent() == Some(template_dir.as_path())
+            && config.templates_sharing_path(key).is_empty()
+            && old_path != new_path
+        {
+            if new_path.exists() {
+                return Err(RenameTemplateError::IoErr(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", new_path.to_string_lossy()),
+                )));
+            }
+            std::fs::rename(&old_path, &new_path).map_err(RenameTemplateError::IoErr)?;
+            config.templates.get_mut(key).unwrap().path = new_path;
+        }
+
+        let mut template = config.templates.remove(key).unwrap();
+        template.name = new_name.to_string();
+        config.templates.insert(new_key, template);
+        for other in config.templates.values_mut() {
+            for include in other.includes.iter_mut() {
+                if *include == old_name {
+                    *include = new_name.to_string();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes a template from the `Config` in memory, moving its directory to the
+    /// `.trash` directory under the templates directory, from where it can be
+    /// restored with [`ConfigStore::restore_template`].
+    fn trash_template(
+        &mut self,
+        key: &TemplateKey,
+    ) -> Result<TrashedTemplate, DeleteTemplateError> {
+        let trash_dir = self
+            .get_template_dir()
+            .map_err(DeleteTemplateError::IoErr)?
+            .join(TRASH_DIR);
+        let config = self.config_mut();
+        let template = match config.templates.get(key) {
+            Some(template) => template,
+            None => return Err(DeleteTemplateError::NoTemplate),
+        };
+        let sharing = config.templates_sharing_path(key);
+        if !sharing.is_empty() {
+            return Err(DeleteTemplateError::SharedPath(
+                sharing.into_iter().map(str::to_string).collect(),
+            ));
+        }
+
+        let trash_path = trash_dir.join(format!(
+            "{}-{}",
+            template.name,
+            chrono::Local::now().format("%Y%m%d%H%M%S")
+        ));
+        std::fs::create_dir_all(&trash_dir).map_err(DeleteTemplateError::IoErr)?;
+        std::fs::rename(&template.path, &trash_path).map_err(DeleteTemplateError::IoErr)?;
+
+        Ok(TrashedTemplate {
+            key: *key,
+            template: config.templates.remove(key).unwrap(),
+            trash_path,
+        })
+    }
+
+    /// Undoes [`ConfigStore::trash_template`], moving the template's directory back
+    /// into place, and restoring the template and its aliases (unless the alias has
+    /// since been taken).
+    fn restore_template(&mut self, trashed: &TrashedTemplate) -> Result<(), RestoreTemplateError> {
+        let config = self.config_mut();
+        if config.templates.contains_key(&trashed.key) {
+            return Err(RestoreTemplateError::NameTaken);
+        }
+        if trashed.template.path.exists() {
+            return Err(RestoreTemplateError::PathTaken);
+        }
+        std::fs::rename(&trashed.trash_path, &trashed.template.path)
+            .map_err(RestoreTemplateError::IoErr)?;
+
+        let mut template = trashed.template.clone();
+        template
+            .aliases
+            .retain(|alias| !config.is_name_or_alias(alias));
+        config.templates.insert(trashed.key, template);
+        Ok(())
+    }
+}
+
+/// The name of the directory, under the templates directory, where deleted
+/// templates are moved.
+pub const TRASH_DIR: &str = ".trash";
+
+/// A template deleted with [`ConfigStore::trash_template`].
+pub struct TrashedTemplate {
+    pub key: TemplateKey,
+    pub template: Template,
+    /// Where the template's directory was moved to.
+    pub trash_path: PathBuf,
+}
+
 /// Struct coupling the serializable, in-memory representation of the
 /// program's configuration `Config`, with information about its file
 /// representation.
 pub struct LoadedConfig {
     pub config: Config,
     pub path: PathBuf,
+    /// Overrides the directory where templates are stored, which is otherwise
+    /// the `templates` directory under `path`.
+    pub template_dir: Option<PathBuf>,
 }
 
 impl LoadedConfig {
@@ -191,26 +778,97 @@ impl LoadedConfig {
     /// instantiated instead.
     pub fn load_from_path(path: PathBuf) -> Result<Self, LoadConfigError> {
         let config = Config::load_from_path(&path)?.unwrap_or_default();
-        Ok(LoadedConfig { config, path })
+        let mut loaded = LoadedConfig {
+            config,
+            path,
+            template_dir: None,
+        };
+        loaded.load_extra_templates();
+        Ok(loaded)
+    }
+
+    /// Adds the templates in the extra template directories to the in-memory
+    /// configuration, after the registered ones. A template whose name is taken
+    /// (by a registered template, or one in an earlier directory) is set aside in
+    /// `shadowed_templates` instead.
+    ///
+    /// Directories that cannot be read are skipped, as are hidden subdirectories.
+    fn load_extra_templates(&mut self) {
+        let roots = self
+            .config
+            .extra_template_dirs
+            .iter()
+            .map(|dir| {
+                let expanded = shellexpand::full(dir)
+                    .map(|dir| dir.into_owned())
+                    .unwrap_or_else(|_| dir.clone());
+                self.path.join(expanded)
+            })
+            .collect::<Vec<PathBuf>>();
+        for root in roots {
+            let mut entries = match root.read_dir() {
+                Ok(entries) => entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .collect::<Vec<_>>(),
+                Err(_) => continue,
+            };
+            entries.sort();
+            for path in entries.into_iter().filter(|path| path.is_dir()) {
+                let name = match path.file_name().and_then(|name| name.to_str()) {
+                    Some(name) if !name.starts_with('.') => name.to_string(),
+                    _ => continue,
+                };
+                let key = Config::get_template_key(&name);
+                let template = Template {
+                    name,
+                    path,
+                    order: self.config.next_order(),
+                    external: true,
+                    ..Default::default()
+                };
+                match self.config.templates.entry(key) {
+                    std::collections::btree_map::Entry::Occupied(_) => {
+                        self.config.shadowed_templates.push(template)
+                    }
+                    std::collections::btree_map::Entry::Vacant(entry) => {
+                        entry.insert(template);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ConfigStore for LoadedConfig {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
     }
 
     /// Get the template base directory, per this `LoadedConfig`'s base directory.
     ///
     /// As a side effect of this call, if this directory does not exist, it will
     /// be created.
-    pub fn get_template_dir(&self) -> PathBuf {
-        let dir = self.path.join("templates");
+    fn get_template_dir(&self) -> std::io::Result<PathBuf> {
+        let dir = match &self.template_dir {
+            Some(dir) => self.path.join(dir),
+            None => self.path.join("templates"),
+        };
         if !dir.exists() {
-            std::fs::create_dir(&dir).expect("Could not create templates directory.");
+            std::fs::create_dir_all(&dir)?;
         }
-        dir
+        Ok(dir)
     }
 
     /// Serialize the configuration object to disk, according to the path
     /// information in `LoadedConfig`.
     ///
     /// If the JSON file does not exist, it will be created.
-    pub fn write_config(&self) -> Result<(), WriteConfigError> {
+    fn save(&self) -> Result<(), WriteConfigError> {
         let json_path = get_json_path(&self.path);
         if json_path.exists() && !json_path.is_file() {
             return Err(WriteConfigError::NotAFile(json_path.display().to_string()));
@@ -223,21 +881,217 @@ impl LoadedConfig {
         serde_json::to_writer(writer, &self.config)
             .map_err(|e| WriteConfigError::BadSerialization(e, json_path.display().to_string()))
     }
+}
 
-    /// Deltes a template from the `Config` in memory, removing the corresponding saved
-    /// directory in the templates directory.
-    pub fn delete_template<'key>(
-        &mut self,
-        key: &'key TemplateKey,
-    ) -> Result<(), DeleteTemplateError<'key>> {
-        if !self.config.templates.contains_key(key) {
-            Err(DeleteTemplateError::NoTemplate(key))
-        } else if let Err(err) =
-            std::fs::remove_dir_all(self.config.templates.remove(key).unwrap().path)
-        {
-            Err(DeleteTemplateError::IoErr(err))
-        } else {
-            Ok(())
+/// A configuration that is only kept in memory, with its templates stored in a
+/// given directory, for testing commands without touching the configuration file.
+#[cfg(test)]
+pub struct MemoryConfig {
+    pub config: Config,
+    pub template_dir: PathBuf,
+    /// How many times the configuration was saved.
+    pub saves: std::cell::Cell<usize>,
+}
+
+#[cfg(test)]
+impl MemoryConfig {
+    pub fn new(template_dir: PathBuf) -> Self {
+        MemoryConfig {
+            config: Config::default(),
+            template_dir,
+            saves: std::cell::Cell::new(0),
         }
     }
 }
+
+#[cfg(test)]
+impl ConfigStore for MemoryConfig {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    fn get_template_dir(&self) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.template_dir)?;
+        Ok(self.template_dir.clone())
+    }
+
+    fn save(&self) -> Result<(), WriteConfigError> {
+        self.saves.set(self.saves.get() + 1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_dirs_fall_back_without_a_home() {
+        // With `HOME` unset, there is neither a configuration nor a home directory.
+        let dir = tempfile::tempdir().unwrap();
+        let fallback = dir.path().join(".boyl");
+        let chosen = first_usable_dir(&[None, None, Some(fallback.clone())]);
+        assert_eq!(chosen, Some(fallback.clone()));
+        assert!(fallback.is_dir());
+        assert_eq!(first_usable_dir(&[None, None, None]), None);
+    }
+
+    #[test]
+    fn config_dirs_that_cannot_be_created_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        let fallback = dir.path().join(".boyl");
+        let chosen = first_usable_dir(&[Some(file.join("boyl")), Some(fallback.clone())]);
+        assert_eq!(chosen, Some(fallback));
+    }
+
+    /// Registers a template named `name`, with its directory (holding a single
+    /// file) in the templates directory.
+    fn add_template(store: &mut MemoryConfig, name: &str) -> TemplateKey {
+        let path = store.get_template_dir().unwrap().join(name);
+        std::fs::create_dir(&path).unwrap();
+        std::fs::write(path.join("file.txt"), name).unwrap();
+        let key = Config::get_template_key(name);
+        let template = Template {
+            name: name.to_string(),
+            path,
+            order: store.config.next_order(),
+            ..Default::default()
+        };
+        store.config.templates.insert(key, template);
+        key
+    }
+
+    #[test]
+    fn template_keys_are_fnv_1a_hashes() {
+        // Reference values of the 64-bit FNV-1a hash.
+        assert_eq!(Config::get_template_key(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(Config::get_template_key("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(Config::get_template_key("foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn configurations_survive_a_serde_round_trip() {
+        let mut config = Config::default();
+        let template = Template {
+            name: "rust".to_string(),
+            description: Some("A Rust project".to_string()),
+            path: PathBuf::from("/templates/rust"),
+            license: Some("MIT".to_string()),
+            includes: vec!["base".to_string()],
+            order: 3,
+            tags: vec!["cli".to_string()],
+            aliases: vec!["rs".to_string()],
+            use_count: 2,
+            ..Default::default()
+        };
+        config
+            .templates
+            .insert(Config::get_template_key("rust"), template);
+        let external = Template {
+            name: "found".to_string(),
+            external: true,
+            ..Default::default()
+        };
+        config
+            .templates
+            .insert(Config::get_template_key("found"), external);
+
+        let json = serde_json::to_value(&config).unwrap();
+        let stored = &json["templates"][Config::get_template_key("rust").to_string()];
+        assert_eq!(stored["path"], "/templates/rust");
+        assert_eq!(json["templates"].as_object().unwrap().len(), 1);
+
+        let read = serde_json::from_value::<Config>(json).unwrap();
+        let (key, template) = read.find_template("rs").unwrap();
+        assert_eq!(key, Config::get_template_key("rust"));
+        assert_eq!(template.description.as_deref(), Some("A Rust project"));
+        assert_eq!(template.path, PathBuf::from("/templates/rust"));
+        assert_eq!(template.license.as_deref(), Some("MIT"));
+        assert_eq!(template.includes, vec!["base"]);
+        assert_eq!(template.order, 3);
+        assert_eq!(template.tags, vec!["cli"]);
+        assert_eq!(template.use_count, 2);
+        assert_eq!(read.version, config.version);
+    }
+
+    #[test]
+    fn renaming_moves_the_template_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = MemoryConfig::new(dir.path().join("templates"));
+        let key = add_template(&mut store, "old");
+        let includer = add_template(&mut store, "includer");
+        store
+            .config
+            .templates
+            .get_mut(&includer)
+            .unwrap()
+            .includes
+            .push("old".to_string());
+
+        assert!(store.rename_template(&key, " new ").is_ok());
+        let (_, renamed) = store.config.find_template("new").unwrap();
+        assert_eq!(renamed.path, store.template_dir.join("new"));
+        assert!(renamed.path.join("file.txt").is_file());
+        assert!(!store.template_dir.join("old").exists());
+        assert_eq!(store.config.templates[&includer].includes, vec!["new"]);
+    }
+
+    #[test]
+    fn renaming_to_a_taken_name_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = MemoryConfig::new(dir.path().join("templates"));
+        let key = add_template(&mut store, "first");
+        add_template(&mut store, "second");
+
+        assert!(matches!(
+            store.rename_template(&key, "second"),
+            Err(RenameTemplateError::NameTaken)
+        ));
+        assert!(store.template_dir.join("first").is_dir());
+    }
+
+    #[test]
+    fn renaming_to_a_path_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = MemoryConfig::new(dir.path().join("templates"));
+        let key = add_template(&mut store, "inside");
+
+        for name in ["../outside", "/tmp/outside", "a/b", "a\\b", "..", "."] {
+            assert!(
+                matches!(
+                    store.rename_template(&key, name),
+                    Err(RenameTemplateError::InvalidName)
+                ),
+                "{} was accepted",
+                name
+            );
+        }
+        assert!(store.template_dir.join("inside").is_dir());
+        assert!(!dir.path().join("outside").exists());
+        assert!(Config::is_valid_template_name("in.side"));
+    }
+
+    #[test]
+    fn trashed_templates_can_be_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = MemoryConfig::new(dir.path().join("templates"));
+        let key = add_template(&mut store, "doomed");
+
+        let trashed = store.trash_template(&key).ok().unwrap();
+        assert!(store.config.templates.is_empty());
+        assert!(trashed
+            .trash_path
+            .starts_with(store.template_dir.join(TRASH_DIR)));
+        assert!(!store.template_dir.join("doomed").exists());
+
+        assert!(store.restore_template(&trashed).is_ok());
+        assert!(store.config.templates.contains_key(&key));
+        assert!(store.template_dir.join("doomed/file.txt").is_file());
+    }
+}
@@ -1,56 +1,505 @@
-use crate::ui::spinner::Spinner;
+use crate::walkdir::WalkEntry;
+use crate::{ui::spinner::Spinner, verbosity::Verbosity};
 use colored::Colorize;
 use futures::{Stream, StreamExt};
-use std::path::Path;
+use std::{
+    cell::Cell,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 use termion::terminal_size;
-use tokio::fs::DirEntry;
 
-async fn copy_from_to(from: &Path, to: &Path) -> Result<(), tokio::io::Error> {
-    if from.is_dir() {
-        if !to.exists() {
-            tokio::fs::create_dir(to).await?;
+/// When progress cannot be drawn in place, a line is printed every this many files.
+const PROGRESS_LINE_EVERY: usize = 100;
+
+/// Options controlling the behaviour of [`recursive_copy`].
+pub struct CopyOptions {
+    /// Whether to draw a spinner with the file being copied.
+    pub show_progress: bool,
+    /// How many files may be copied at once.
+    pub jobs: usize,
+    /// Whether to give the copies the modification times of the originals.
+    pub preserve_times: bool,
+    /// Whether to stop at the first file that cannot be copied, removing the
+    /// target directory. Otherwise, failures are collected, and the remaining
+    /// files are still copied.
+    pub strict: bool,
+    /// From `Verbosity::Some` on, every file copied is printed (in place of the
+    /// progress).
+    pub verbosity: Verbosity,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions {
+            show_progress: true,
+            jobs: default_jobs(),
+            preserve_times: false,
+            strict: false,
+            verbosity: Verbosity::None,
+        }
+    }
+}
+
+/// The default number of concurrent copies: the available parallelism of the
+/// machine, or 4 if it cannot be determined.
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+/// Summary of a finished [`recursive_copy`].
+#[derive(Default)]
+pub struct CopyStats {
+    /// Number of (non-directory) files copied.
+    pub files: usize,
+    /// Total size of the copied files, in bytes.
+    pub bytes: u64,
+    /// The files that could not be copied, and why.
+    pub failures: Vec<(PathBuf, std::io::Error)>,
+}
+
+/// Why a [`recursive_copy`] stopped before copying every file.
+#[derive(Debug)]
+pub enum CopyError {
+    /// The directory to copy from does not exist, or is not a directory.
+    SourceMissing(PathBuf),
+    /// A file could not be copied (in strict mode), and the target directory was
+    /// removed.
+    Io(PathBuf, std::io::Error),
+    /// A file could not be copied (in strict mode), and neither could the target
+    /// directory be removed afterwards.
+    CleanupFailed {
+        file: PathBuf,
+        error: std::io::Error,
+        target: PathBuf,
+        cleanup_error: std::io::Error,
+    },
+}
+
+impl Display for CopyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CopyError::SourceMissing(path) => {
+                write!(
+                    f,
+                    "{} does not exist, or is not a directory.",
+                    path.to_string_lossy()
+                )
+            }
+            CopyError::Io(file, error) => write!(
+                f,
+                "Could not copy {}, with error: {}\n\
+                What was copied has been removed.",
+                file.to_string_lossy(),
+                error
+            ),
+            CopyError::CleanupFailed {
+                file,
+                error,
+                target,
+                cleanup_error,
+            } => write!(
+                f,
+                "Could not copy {}, with error: {}\n\
+                What was copied could not be removed either, with error: {}\n\
+                You may want to remove {} yourself.",
+                file.to_string_lossy(),
+                error,
+                cleanup_error,
+                target.to_string_lossy()
+            ),
         }
+    }
+}
+
+/// Copies `from` to `to`, returning the number of bytes copied (or `None`,
+/// for directories).
+///
+/// Symbolic links are recreated, pointing to the same place, rather than having
+/// what they point to copied (which is what happens if the walk followed them).
+///
+/// Files keep their permissions (on Unix, the mode bits; elsewhere, whether they are
+/// read-only), and, if `preserve_times` is set, their modification time.
+async fn copy_from_to(
+    from: &WalkEntry,
+    to: &Path,
+    preserve_times: bool,
+) -> Result<Option<u64>, tokio::io::Error> {
+    // Copies run concurrently, so a directory may already have been created for
+    // one of its children (and the other way around).
+    if from.metadata.is_dir() {
+        tokio::fs::create_dir_all(to).await?;
+        Ok(None)
     } else {
         let parent = to.parent().unwrap();
         if !parent.exists() {
             tokio::fs::create_dir_all(parent).await?;
         }
-        tokio::fs::copy(from, to).await?;
+        if from.metadata.file_type().is_symlink() {
+            copy_symlink(&from.path, to).await?;
+            Ok(Some(0))
+        } else {
+            let bytes = tokio::fs::copy(&from.path, to).await?;
+            tokio::fs::set_permissions(to, from.metadata.permissions()).await?;
+            if preserve_times {
+                let mtime = filetime::FileTime::from_last_modification_time(&from.metadata);
+                filetime::set_file_mtime(to, mtime)?;
+            }
+            Ok(Some(bytes))
+        }
     }
-    Ok(())
+}
+
+/// Creates a symbolic link at `to` pointing to where the link at `from` points,
+/// replacing any file already at `to` (e.g., from an earlier template layer).
+async fn copy_symlink(from: &Path, to: &Path) -> Result<(), tokio::io::Error> {
+    let link_target = tokio::fs::read_link(from).await?;
+    if let Ok(existing) = tokio::fs::symlink_metadata(to).await {
+        if !existing.is_dir() {
+            tokio::fs::remove_file(to).await?;
+        }
+    }
+    make_symlink(&link_target, to, from.is_dir()).await
+}
+
+#[cfg(unix)]
+async fn make_symlink(link_target: &Path, link: &Path, _is_dir: bool) -> tokio::io::Result<()> {
+    tokio::fs::symlink(link_target, link).await
+}
+
+#[cfg(windows)]
+async fn make_symlink(link_target: &Path, link: &Path, is_dir: bool) -> tokio::io::Result<()> {
+    if is_dir {
+        tokio::fs::symlink_dir(link_target, link).await
+    } else {
+        tokio::fs::symlink_file(link_target, link).await
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn make_symlink(_link_target: &Path, _link: &Path, _is_dir: bool) -> tokio::io::Result<()> {
+    Err(tokio::io::Error::new(
+        tokio::io::ErrorKind::Other,
+        "Symbolic links are not supported on this platform.",
+    ))
 }
 
 /// Copies files within `from_base_dir` (as given by the `files` iterator)
 /// into a new `to_base_dir` directory.
+///
+/// Up to `options.jobs` files are copied at once; the progress is reported as
+/// each copy finishes. To report it as a fraction of the total, `files` is
+/// collected before anything is copied.
+///
+/// Files that cannot be copied are listed in the returned `CopyStats`, unless
+/// `options.strict` is set, in which case no more copies are started, and once the
+/// ones under way finish, `to_base_dir` is removed and an error is returned
+/// instead.
 pub async fn recursive_copy(
     from_base_dir: &'_ Path,
     to_base_dir: &'_ Path,
-    mut files: impl Stream<Item = DirEntry> + Unpin,
-) {
+    files: impl Stream<Item = WalkEntry> + Unpin,
+    options: &CopyOptions,
+) -> Result<CopyStats, CopyError> {
+    if !from_base_dir.is_dir() {
+        return Err(CopyError::SourceMissing(from_base_dir.to_path_buf()));
+    }
+    let files = files
+        .filter(|entry| futures::future::ready(entry.path != from_base_dir))
+        .collect::<Vec<WalkEntry>>()
+        .await;
+    let total = files.len();
+
+    let mut stats = CopyStats::default();
     let mut spinner = Spinner::new();
-    let terminal_width = terminal_size().map(|(w, _)| w).unwrap_or(0);
-    while let Some(file) = files.next().await {
-        let file = file.path();
-        if file == from_base_dir {
+    let terminal_width = terminal_size().map(|(w, _)| w).unwrap_or(0) as usize;
+    let preserve_times = options.preserve_times;
+    // Set on the first failure in strict mode, so that no further copies start.
+    let stopped = Cell::new(false);
+    let mut copies = futures::stream::iter(files)
+        .take_while(|_| futures::future::ready(!stopped.get()))
+        .map(|entry| async move {
+            let base_file = entry.path.strip_prefix(from_base_dir).unwrap();
+            let result = copy_from_to(&entry, &to_base_dir.join(base_file), preserve_times).await;
+            (entry, result)
+        })
+        .buffer_unordered(std::cmp::max(options.jobs, 1));
+    let mut done = 0;
+    let show_progress = options.show_progress && options.verbosity == Verbosity::None;
+    let in_place = show_progress && spinner.enabled();
+    let mut strict_failure = None;
+    while let Some((entry, result)) = copies.next().await {
+        // After a strict failure, the copies under way are only waited for, as
+        // they could otherwise write into `to_base_dir` after it is removed.
+        if strict_failure.is_some() {
             continue;
         }
-        let base_file = file.strip_prefix(from_base_dir).unwrap();
+        done += 1;
+        if in_place {
+            let file_name = entry.path.to_string_lossy();
+            if terminal_width == 0 {
+                let spinner_symbol = spinner.tick();
+                eprint!("{} {}\r", spinner_symbol, spinner_symbol);
+            } else {
+                let counter = format!(
+                    "[ {:>width$}/{} ] ({:>3}%) ",
+                    done,
+                    total,
+                    done * 100 / total,
+                    width = total.to_string().len()
+                );
+                let name_width = terminal_width.saturating_sub(counter.len() + 1);
+                let mut start = file_name.len().saturating_sub(name_width);
+                while !file_name.is_char_boundary(start) {
+                    start += 1;
+                }
+                let file_name = &file_name[start..];
+                eprint!(
+                    "{}{:<name_width$}\r",
+                    counter,
+                    file_name,
+                    name_width = name_width
+                );
+            }
+            std::io::Write::flush(&mut std::io::stderr()).ok();
+        } else if show_progress && (done % PROGRESS_LINE_EVERY == 0 || done == total) {
+            eprintln!("[ {}/{} ] ({}%)", done, total, done * 100 / total);
+        }
+
+        match result {
+            Ok(Some(bytes)) => {
+                if options.verbosity >= Verbosity::Some {
+                    let base_file = entry.path.strip_prefix(from_base_dir).unwrap();
+                    eprintln!("{} {}", "Copied".dimmed(), base_file.to_string_lossy());
+                }
+                stats.files += 1;
+                stats.bytes += bytes;
+            }
+            Ok(None) => {}
+            Err(error) if options.strict => {
+                stopped.set(true);
+                strict_failure = Some((entry.path, error));
+            }
+            Err(e) => stats.failures.push((entry.path, e)),
+        }
+    }
+    if let Some((file, error)) = strict_failure {
+        if in_place {
+            eprintln!("{}\r", " ".repeat(terminal_width));
+        }
+        return Err(match std::fs::remove_dir_all(to_base_dir) {
+            Ok(()) => CopyError::Io(file, error),
+            Err(cleanup_error) => CopyError::CleanupFailed {
+                file,
+                error,
+                target: to_base_dir.to_path_buf(),
+                cleanup_error,
+            },
+        });
+    }
+    if in_place {
+        eprint!("{}\r", " ".repeat(terminal_width));
+    }
+    // A summary is only of use to someone watching.
+    if options.show_progress && spinner.enabled() {
+        let files = match stats.files {
+            1 => "1 file".to_string(),
+            n => format!("{} files", n),
+        };
+        let summary = format!(
+            "Copied {} ({}) in {:.1}s",
+            files,
+            format_size(stats.bytes),
+            spinner.elapsed().as_secs_f64()
+        );
+        eprintln!("{}", summary.dimmed());
+    }
+    Ok(stats)
+}
+
+/// A size in bytes, in the largest (decimal) unit that keeps it above 1.
+fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit + 1 < UNITS.len() {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
 
-        let file_name = file.to_string_lossy();
-        let file_name = &file_name[file_name
-            .len()
-            .saturating_sub(terminal_width.saturating_sub(8) as usize)..];
-        let whitespace = " ".repeat((terminal_width as usize).saturating_sub(file_name.len() + 10));
-        let spinner_symbol = spinner.tick();
-        print!("{} {}{} {}\r", spinner_symbol, file_name, whitespace, spinner_symbol);
+/// Prints a summary of the files that could not be copied.
+pub fn print_failures(failures: &[(PathBuf, std::io::Error)]) {
+    let count = match failures.len() {
+        1 => "1 file".to_string(),
+        n => format!("{} files", n),
+    };
+    eprintln!("{}", format!("{} could not be copied:", count).red());
+    for (path, err) in failures {
+        eprintln!("  {}: {}", path.to_string_lossy(), err);
+    }
+}
 
-        let target_file = to_base_dir.join(base_file);
+/// Removes every directory within `base_dir` that is empty, bottom-up, such that
+/// directories containing only empty directories are removed as well.
+///
+/// `base_dir` itself is never removed. Symbolic links are not followed.
+pub fn prune_empty_dirs(base_dir: &Path) -> std::io::Result<()> {
+    for entry in base_dir.read_dir()? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            prune_empty_dirs(&entry.path())?;
+            if entry.path().read_dir()?.next().is_none() {
+                std::fs::remove_dir(entry.path())?;
+            }
+        }
+    }
+    Ok(())
+}
 
-        if let Err(e) = copy_from_to(&file, &target_file).await {
-            println!("{}", "Some error occurred; cleaning up the templates directory first...".red());
-            std::fs::remove_dir_all(to_base_dir).ok();
-            panic!("{}", e);
+/// Adds the executable permission bits to every file within `base_dir` whose path,
+/// relative to `base_dir`, matches any of the given `patterns`.
+///
+/// This is a no-op on platforms without Unix permissions.
+pub fn set_executable(base_dir: &Path, patterns: &[glob::Pattern]) -> std::io::Result<()> {
+    fn visit(base_dir: &Path, dir: &Path, patterns: &[glob::Pattern]) -> std::io::Result<()> {
+        for entry in dir.read_dir()? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                visit(base_dir, &path, patterns)?;
+            } else if file_type.is_file() {
+                let relative_path = path.strip_prefix(base_dir).unwrap();
+                if patterns.iter().any(|p| p.matches_path(relative_path)) {
+                    add_executable_bits(&path)?;
+                }
+            }
         }
+        Ok(())
+    }
+
+    visit(base_dir, base_dir, patterns)
+}
+
+#[cfg(unix)]
+fn add_executable_bits(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    std::fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn add_executable_bits(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Copies everything in `from` into `to`, which must not exist.
+    fn copy_tree(from: &Path, to: &Path, preserve_times: bool) -> CopyStats {
+        let options = CopyOptions {
+            show_progress: false,
+            jobs: 2,
+            preserve_times,
+            strict: true,
+            verbosity: Verbosity::None,
+        };
+        let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+        runtime.block_on(async {
+            let files = Box::pin(
+                crate::walkdir::visit(from, false)
+                    .filter_map(|entry| futures::future::ready(entry.ok())),
+            );
+            match recursive_copy(from, to, files, &options).await {
+                Ok(stats) => stats,
+                Err(err) => panic!("{}", err),
+            }
+        })
+    }
+
+    #[test]
+    fn strict_failures_remove_the_target_once_copies_finish() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("from");
+        std::fs::create_dir(&from).unwrap();
+        for i in 0..200 {
+            std::fs::write(from.join(format!("{}.txt", i)), "contents").unwrap();
+        }
+        let to = dir.path().join("to");
+        let options = CopyOptions {
+            show_progress: false,
+            jobs: 8,
+            preserve_times: false,
+            strict: true,
+            verbosity: Verbosity::None,
+        };
+        let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+        let result = runtime.block_on(async {
+            let files = crate::walkdir::visit(&from, false)
+                .filter_map(|entry| futures::future::ready(entry.ok()))
+                .collect::<Vec<WalkEntry>>()
+                .await;
+            // The first file disappears after being listed, so that copying it fails
+            // while the others are under way.
+            let missing = files.iter().find(|entry| entry.path != from).unwrap();
+            std::fs::remove_file(&missing.path).unwrap();
+            recursive_copy(&from, &to, futures::stream::iter(files), &options).await
+        });
+        assert!(matches!(result, Err(CopyError::Io(..))));
+        drop(runtime);
+        assert!(!to.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn executable_files_stay_executable() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("from");
+        std::fs::create_dir_all(from.join("bin")).unwrap();
+        let script = from.join("bin/run.sh");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(from.join("notes.txt"), "").unwrap();
+        std::fs::set_permissions(
+            from.join("notes.txt"),
+            std::fs::Permissions::from_mode(0o640),
+        )
+        .unwrap();
+
+        let to = dir.path().join("to");
+        let stats = copy_tree(&from, &to, false);
+        assert_eq!(stats.files, 2);
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&to.join("bin/run.sh")), 0o755);
+        assert_eq!(mode(&to.join("notes.txt")), 0o640);
+    }
+
+    #[test]
+    fn modification_times_are_kept_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("from");
+        std::fs::create_dir(&from).unwrap();
+        let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        std::fs::write(from.join("old.txt"), "old").unwrap();
+        filetime::set_file_mtime(from.join("old.txt"), old).unwrap();
+
+        let mtime = |path: &Path| {
+            filetime::FileTime::from_last_modification_time(&std::fs::metadata(path).unwrap())
+        };
+        copy_tree(&from, &dir.path().join("kept"), true);
+        assert_eq!(mtime(&dir.path().join("kept/old.txt")), old);
+        copy_tree(&from, &dir.path().join("fresh"), false);
+        assert!(mtime(&dir.path().join("fresh/old.txt")) > old);
     }
-    println!("{}\r", " ".repeat(terminal_width as usize));
 }
@@ -0,0 +1,50 @@
+use std::path::Path;
+
+/// A line of a `.gitignore` file, translated to a glob pattern relative to the
+/// directory of the `.gitignore`.
+pub struct GitignorePattern {
+    pub glob: String,
+    /// Whether the line started with `!`, re-including what it matches.
+    pub negated: bool,
+}
+
+/// The name of the file, at the root of a template's source, whose patterns `make`
+/// always excludes. It is written like a `.gitignore`, but is only read by boyl.
+pub const BOYLIGNORE: &str = ".boylignore";
+
+/// Reads the ignore file `file_name` (a `.gitignore`, or a [`BOYLIGNORE`]) at the
+/// root of `dir`, if there is one.
+///
+/// This is an approximation of git's rules: a trailing `/` (matching only
+/// directories) is dropped, and negations re-include what they match regardless
+/// of the order of the lines.
+pub fn read_patterns(dir: &Path, file_name: &str) -> Vec<GitignorePattern> {
+    let contents = match std::fs::read_to_string(dir.join(file_name)) {
+        Ok(contents) => contents,
+        Err(_) => return vec![],
+    };
+    contents.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<GitignorePattern> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let line = line.trim_end_matches('/');
+    if line.is_empty() {
+        return None;
+    }
+    // Patterns with a slash (other than a trailing one) are relative to the
+    // `.gitignore`; others match at any depth.
+    let glob = match line.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if line.contains('/') => line.to_string(),
+        None => format!("**/{}", line),
+    };
+    Some(GitignorePattern { glob, negated })
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+/// Licenses bundled with boyl, as pairs of SPDX identifiers and license texts.
+///
+/// The texts may contain the `{{year}}` and `{{author}}` placeholders, which
+/// are substituted when the license is rendered.
+const LICENSES: &[(&str, &str)] = &[
+    ("MIT", include_str!("licenses/MIT.txt")),
+    ("ISC", include_str!("licenses/ISC.txt")),
+    ("0BSD", include_str!("licenses/0BSD.txt")),
+    ("BSD-2-Clause", include_str!("licenses/BSD-2-Clause.txt")),
+    ("BSD-3-Clause", include_str!("licenses/BSD-3-Clause.txt")),
+    ("Unlicense", include_str!("licenses/Unlicense.txt")),
+];
+
+/// File names (without extension, and compared case-insensitively) that are
+/// taken to be a license file already present in a project.
+const LICENSE_FILE_STEMS: &[&str] = &["LICENSE", "LICENCE", "COPYING"];
+
+/// Finds the canonical SPDX identifier of a bundled license, matching the given
+/// identifier case-insensitively.
+pub fn find_license(spdx_id: &str) -> Option<&'static str> {
+    LICENSES
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(spdx_id.trim()))
+        .map(|(id, _)| *id)
+}
+
+/// A human-readable, comma separated, list of the bundled licenses.
+pub fn available_licenses() -> String {
+    LICENSES
+        .iter()
+        .map(|(id, _)| *id)
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+/// Returns the text of the given bundled license, with the year and author
+/// placeholders substituted, or `None` if the license is not bundled.
+pub fn render_license(spdx_id: &str, year: i32, author: &str) -> Option<String> {
+    let spdx_id = find_license(spdx_id)?;
+    let (_, text) = LICENSES.iter().find(|(id, _)| *id == spdx_id)?;
+    Some(
+        text.replace("{{year}}", &year.to_string())
+            .replace("{{author}}", author),
+    )
+}
+
+/// Whether the given directory already contains something that looks like a
+/// license file (e.g. `LICENSE`, `LICENSE.md` or `COPYING`).
+pub fn has_license_file(dir: &Path) -> bool {
+    let entries = match dir.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    entries.flatten().any(|entry| {
+        entry
+            .path()
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_uppercase())
+            .is_some_and(|stem| LICENSE_FILE_STEMS.contains(&stem.as_str()))
+    })
+}
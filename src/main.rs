@@ -15,13 +15,20 @@ macro_rules! clone_move {
     };
 }
 
+mod binary;
 mod cmd;
 mod config;
 mod copy;
+mod gitignore;
+mod license;
+mod migrations;
+mod substitute;
 mod template;
 mod ui;
 mod userbool;
 mod userpath;
+mod verbosity;
+mod version;
 mod walkdir;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -29,6 +36,13 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 #[derive(FromArgs)]
 /// Quickly create boilerplate projects and templates.
 struct Boyl {
+    #[argh(option)]
+    /// the configuration profile to use [default: $BOYL_PROFILE]
+    profile: Option<String>,
+    #[argh(switch)]
+    /// never color the output (it is also not colored when $NO_COLOR is set, or
+    /// when the output is not a terminal)
+    no_color: bool,
     #[argh(subcommand)]
     command: Command,
 }
@@ -41,6 +55,14 @@ enum Command {
     Make(MakeCommand),
     New(NewCommand),
     Edit(EditCommand),
+    Alias(AliasCommand),
+    Rename(RenameCommand),
+    Describe(DescribeCommand),
+    Open(OpenCommand),
+    Export(ExportCommand),
+    Import(ImportCommand),
+    Config(ConfigCommand),
+    Doctor(DoctorCommand),
     Xoxo(XoxoCommand),
     Version(VersionCommand),
 }
@@ -48,7 +70,28 @@ enum Command {
 #[derive(FromArgs, PartialEq, Debug)]
 /// Lists the available templates.
 #[argh(subcommand, name = "list")]
-struct ListCommand {}
+struct ListCommand {
+    #[argh(switch)]
+    /// print the templates as a JSON array, instead of the usual output
+    json: bool,
+    #[argh(option)]
+    /// only list the templates with this tag
+    tag: Option<String>,
+    #[argh(switch)]
+    /// show how many files each template has, their total size, and how
+    /// many times it was used
+    stats: bool,
+    #[argh(option)]
+    /// how to order the templates: custom (as set in boyl edit), name, recent
+    /// (most recently used first), or popular (most used first)
+    /// [default: custom]
+    sort: Option<cmd::list::SortOrder>,
+    #[argh(option)]
+    /// how to show the times printed with --stats: relative (e.g. 3 days
+    /// ago), iso, or a strftime format string like %Y-%m-%d
+    /// [default: relative]
+    time_format: Option<cmd::list::TimeFormat>,
+}
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Shows the tree structure of a template.
@@ -59,24 +102,86 @@ struct TreeCommand {
     #[argh(positional)]
     /// the project template to examine
     template: String,
+    #[argh(switch)]
+    /// show only text files (and directories)
+    only_text: bool,
+    #[argh(option)]
+    /// instead of showing the tree, print how the files of the given template
+    /// differ from those of this template
+    diff: Option<String>,
+    #[argh(switch)]
+    /// with --diff, also print the changes in the text files common to both
+    content: bool,
+    #[argh(switch)]
+    /// print the tree to stdout, instead of showing it interactively
+    plain: bool,
+    #[argh(option)]
+    /// how many levels of the tree to show [default: all]
+    depth: Option<usize>,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Interactively generates a new template.
 #[argh(subcommand, name = "make")]
 struct MakeCommand {
-    #[argh(positional, short = 'n')]
+    #[argh(positional)]
     /// the name of the new template
-    name: String,
+    name: Option<String>,
+    #[argh(option, short = 'n', long = "name")]
+    /// the name of the new template, in place of the positional argument
+    named: Option<String>,
     #[argh(option, short = 'l', default = "None", from_str_fn(to_some_user_path))]
     /// what directory to copy as a template [default: <current dir.>]
     location: Option<userpath::UserDir>,
     #[argh(option, short = 'd')]
     /// description of the template [default: None]
     description: Option<String>,
+    #[argh(option)]
+    /// SPDX identifier of a license to add to new projects [default: None]
+    license: Option<String>,
+    #[argh(option)]
+    /// version to tag the template with [default: None]
+    template_version: Option<String>,
     #[argh(switch)]
     /// include all files from `location` without asking
     all: bool,
+    #[argh(switch)]
+    /// print the created template as JSON, instead of the usual output
+    json: bool,
+    #[argh(switch)]
+    /// exclude binary files, in addition to those excluded in the picker
+    exclude_binary: bool,
+    #[argh(option)]
+    /// start with the exclusion patterns used to make another template
+    patterns_from: Option<String>,
+    #[argh(switch)]
+    /// do not exclude the files ignored by a `.gitignore` in `location`
+    no_gitignore: bool,
+    #[argh(option)]
+    /// how many files to copy at once [default: number of CPUs]
+    jobs: Option<usize>,
+    #[argh(switch)]
+    /// copy what symbolic links point to, instead of the links themselves
+    follow_symlinks: bool,
+    #[argh(switch)]
+    /// keep the modification times of the copied files
+    preserve: bool,
+    #[argh(switch)]
+    /// stop at the first file that cannot be copied, and remove the template
+    strict: bool,
+    #[argh(switch, short = 'v')]
+    /// print every file copied; given twice, also why each file is included or not
+    verbose: u8,
+    #[argh(switch, short = 'q')]
+    /// print nothing but errors and warnings
+    quiet: bool,
+    #[argh(option)]
+    /// a template whose files are laid down before this template's, in new
+    /// projects; can be given multiple times
+    include: Vec<String>,
+    #[argh(option)]
+    /// a tag to group the template by; can be given multiple times
+    tag: Vec<String>,
 }
 
 /// Wrapper around `userpath::to_user_path` to use with `argh`.
@@ -84,6 +189,24 @@ fn to_some_user_path(path: &str) -> Result<Option<userpath::UserDir>, String> {
     userpath::to_user_path(path).map(Some)
 }
 
+/// Prints an error (and a hint about it, if there is one), and exits with the
+/// given code.
+fn fail(err: &impl std::fmt::Display, hint: Option<String>, code: exitcode::ExitCode) -> ! {
+    eprintln!("{}", err.to_string().red());
+    if let Some(hint) = hint {
+        eprintln!("{}", hint.dimmed());
+    }
+    std::process::exit(code)
+}
+
+/// Parses a `KEY=VALUE` assignment of an environment variable.
+fn to_env_assignment(assignment: &str) -> Result<(String, String), String> {
+    match assignment.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("Expected KEY=VALUE, found {}", assignment)),
+    }
+}
+
 #[derive(FromArgs, PartialEq, Debug)]
 /// Creates a new project.
 ///
@@ -91,14 +214,80 @@ fn to_some_user_path(path: &str) -> Result<Option<userpath::UserDir>, String> {
 #[argh(subcommand, name = "new")]
 struct NewCommand {
     #[argh(positional)]
-    /// the project template to use
-    template: String,
+    /// the project template to use (chosen from a list if not given, when run
+    /// from a terminal)
+    template: Option<String>,
     #[argh(option, short = 'n')]
     /// the name for the new project [default: <template name>]
     name: Option<String>,
     #[argh(option, short = 'l')]
-    /// where to create the new project [default: <current dir.>]
-    location: Option<userpath::UserDir>,
+    /// where to create the new project [default: the location set with boyl
+    /// config set-default-location, or the current dir.]
+    location: Option<userpath::UserPath>,
+    #[argh(switch)]
+    /// create the location directory (and any parents) if it does not exist
+    parents: bool,
+    #[argh(switch)]
+    /// remove directories that end up empty in the new project
+    prune_empty: bool,
+    #[argh(option)]
+    /// mark files matching this pattern as executable (can be repeated)
+    set_executable: Vec<glob::Pattern>,
+    #[argh(option)]
+    /// who to credit in a generated LICENSE file [default: $USER]
+    author: Option<String>,
+    #[argh(switch)]
+    /// print the files that would be created, without creating anything
+    dry_run: bool,
+    #[argh(switch)]
+    /// create the project in a non-empty directory, leaving unrelated files
+    /// alone
+    force: bool,
+    #[argh(switch)]
+    /// like --force, but replace existing files only with template files that
+    /// were modified more recently, without asking (the copied files keep the
+    /// template's modification times)
+    overwrite_if_newer: bool,
+    #[argh(switch)]
+    /// do not run the post-creation commands of the template
+    no_hooks: bool,
+    #[argh(option, from_str_fn(to_env_assignment))]
+    /// set an environment variable for the post-creation commands, as
+    /// KEY=VALUE (can be repeated); these win over the BOYL_VAR_* variables
+    /// boyl sets from the substitution variables
+    hooks_env: Vec<(String, String)>,
+    #[argh(option)]
+    /// use (a shallow clone of) a git repository as the template, instead of a
+    /// registered template
+    from_git: Option<String>,
+    #[argh(switch)]
+    /// use an archive made with boyl export, piped into standard input, as the
+    /// template, instead of a registered template
+    from_stdin_archive: bool,
+    #[argh(option)]
+    /// how many files to copy at once [default: number of CPUs]
+    jobs: Option<usize>,
+    #[argh(switch)]
+    /// copy what symbolic links in the template point to, instead of the
+    /// links themselves
+    follow_symlinks: bool,
+    #[argh(switch)]
+    /// keep the modification times of the template's files
+    preserve: bool,
+    #[argh(switch)]
+    /// stop at the first file that cannot be copied, and remove what was
+    /// copied so far
+    strict: bool,
+    #[argh(switch, short = 'v')]
+    /// print every file copied; given twice, also what each template adds
+    verbose: u8,
+    #[argh(switch, short = 'q')]
+    /// print nothing but errors and warnings
+    quiet: bool,
+    #[argh(switch)]
+    /// print the absolute path of the new project (and nothing else) to
+    /// stdout
+    print_path: bool,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -106,63 +295,443 @@ struct NewCommand {
 #[argh(subcommand, name = "edit")]
 struct EditCommand {}
 
+#[derive(FromArgs, PartialEq, Debug)]
+/// Manages shortcut names for templates.
+///
+/// Aliases can be used wherever a template name is expected.
+#[argh(subcommand, name = "alias")]
+struct AliasCommand {
+    #[argh(subcommand)]
+    action: AliasAction,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand)]
+enum AliasAction {
+    Add(AliasAddCommand),
+    Remove(AliasRemoveCommand),
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Defines a shortcut name for a template.
+#[argh(subcommand, name = "add")]
+struct AliasAddCommand {
+    #[argh(positional)]
+    /// the template to refer to
+    template: String,
+    #[argh(positional)]
+    /// the shortcut name
+    alias: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Removes a shortcut name, leaving the template it refers to alone.
+#[argh(subcommand, name = "remove")]
+struct AliasRemoveCommand {
+    #[argh(positional)]
+    /// the shortcut name to remove
+    alias: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Renames a template.
+#[argh(subcommand, name = "rename")]
+struct RenameCommand {
+    #[argh(positional)]
+    /// the template to rename
+    old: String,
+    #[argh(positional)]
+    /// the new name of the template
+    new: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Changes settings of boyl itself.
+#[argh(subcommand, name = "config")]
+struct ConfigCommand {
+    #[argh(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand)]
+enum ConfigAction {
+    SetDefaultLocation(SetDefaultLocationCommand),
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Sets where new projects are created when no location is given.
+///
+/// A location given with --location, or set by the selected profile, takes
+/// precedence.
+#[argh(subcommand, name = "set-default-location")]
+struct SetDefaultLocationCommand {
+    #[argh(positional, from_str_fn(userpath::to_user_path))]
+    /// the directory to create new projects in
+    location: userpath::UserDir,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Sets the description of a template.
+#[argh(subcommand, name = "describe")]
+struct DescribeCommand {
+    #[argh(positional)]
+    /// the template to describe
+    template: String,
+    #[argh(positional)]
+    /// the new description (an empty string clears it)
+    description: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Prints the directory of a template, e.g. for `cd "$(boyl open foo)"`.
+#[argh(subcommand, name = "open")]
+struct OpenCommand {
+    #[argh(positional)]
+    /// the template to open
+    template: String,
+    #[argh(switch)]
+    /// open the directory in $VISUAL or $EDITOR, instead of printing it
+    editor: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Exports a template as a gzip-compressed tarball.
+#[argh(subcommand, name = "export")]
+struct ExportCommand {
+    #[argh(positional)]
+    /// the template to export
+    template: String,
+    #[argh(positional)]
+    /// where to write the archive (e.g. template.tar.gz)
+    out: std::path::PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Imports a template from a tarball made with `boyl export`.
+#[argh(subcommand, name = "import")]
+struct ImportCommand {
+    #[argh(positional)]
+    /// the archive to import
+    archive: std::path::PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Checks the configuration for problems.
+#[argh(subcommand, name = "doctor")]
+struct DoctorCommand {
+    #[argh(switch)]
+    /// fix the problems that can be fixed automatically
+    fix: bool,
+}
+
 #[derive(FromArgs, PartialEq, Debug)]
 /// Print the current version.
 #[argh(subcommand, name = "version")]
-struct VersionCommand {}
+struct VersionCommand {
+    #[argh(switch)]
+    /// also check whether the configuration was written by this version
+    check: bool,
+}
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Hello!
 #[argh(subcommand, name = "xoxo")]
 struct XoxoCommand {}
 
-fn main() {
-    let command: Boyl = argh::from_env();
+/// Finds the profile selected with `--profile` or, failing that, the `BOYL_PROFILE`
+/// environment variable. If no profile is selected, the default (empty) profile
+/// is returned.
+fn resolve_profile(config: &config::LoadedConfig, name: Option<String>) -> config::Profile {
+    let name = match name.or_else(|| std::env::var("BOYL_PROFILE").ok()) {
+        Some(name) => name,
+        None => return config::Profile::default(),
+    };
+    match config.config.profiles.get(&name) {
+        Some(profile) => profile.clone(),
+        None => {
+            eprintln!("{}", format!("{} is not a known profile.", name).red());
+            eprintln!(
+                "{} {}",
+                "Profiles can be defined in".dimmed(),
+                config::get_json_path(&config.path).to_string_lossy()
+            );
+            std::process::exit(exitcode::USAGE);
+        }
+    }
+}
+
+/// The name given to `make`, either as its positional argument or with `--name`,
+/// exiting if there is not exactly one.
+fn make_template_name(positional: Option<String>, named: Option<String>) -> String {
+    match (positional, named) {
+        (Some(name), None) | (None, Some(name)) => name,
+        (Some(_), Some(_)) => {
+            eprintln!(
+                "{}",
+                "Give the template name either as an argument or with --name, not both.".red()
+            );
+            std::process::exit(exitcode::USAGE);
+        }
+        (None, None) => {
+            eprintln!("{}", "Missing the name of the new template.".red());
+            eprintln!(
+                "{} {}",
+                "For example:".dimmed(),
+                "boyl make my-template".yellow()
+            );
+            std::process::exit(exitcode::USAGE);
+        }
+    }
+}
+
+/// Expands `~` and environment variables in a path given in a profile.
+fn expand_profile_path(path: &str) -> std::path::PathBuf {
+    match path.parse::<userpath::UserPath>() {
+        Ok(path) => path.path_buf,
+        Err(err) => {
+            eprintln!("{}", "Error in the selected profile:".red());
+            eprintln!("{}", err.to_string().red());
+            std::process::exit(exitcode::CONFIG);
+        }
+    }
+}
 
+/// Loads the configuration from `BOYL_CONFIG`, or the default configuration
+/// directory, exiting if it cannot be loaded.
+fn load_config() -> config::LoadedConfig {
     let config_path = std::env::var("BOYL_CONFIG").map_or_else(
-        |_| default_config_dir(),
+        |_| match default_config_dir() {
+            Some(path) => path,
+            None => {
+                eprintln!(
+                    "{}",
+                    "Could not find or create a directory for the configuration.".red()
+                );
+                eprintln!(
+                    "{} {} {}",
+                    "Set".dimmed(),
+                    "BOYL_CONFIG".yellow(),
+                    "to the directory boyl should use.".dimmed()
+                );
+                std::process::exit(exitcode::CONFIG);
+            }
+        },
         |path| match userpath::to_user_path(&path) {
             Ok(path) => path.path_buf,
             Err(msg) => {
-                println!("{}", msg);
+                eprintln!("{}", msg);
                 std::process::exit(exitcode::CONFIG);
             }
         },
     );
 
-    let mut config = match config::LoadedConfig::load_from_path(config_path) {
+    match config::LoadedConfig::load_from_path(config_path) {
         Ok(config) => config,
         Err(err) => {
-            println!("{}", "Error loading configuration:".red());
-            println!("{}", &err.to_string().red());
+            eprintln!("{}", "Error loading configuration:".red());
+            eprintln!("{}", &err.to_string().red());
             std::process::exit(exitcode::USAGE);
         }
+    }
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<String>>();
+    let arg_strs = args.iter().map(String::as_str).collect::<Vec<&str>>();
+    let program_name = std::path::Path::new(arg_strs[0])
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(arg_strs[0]);
+    let command = match Boyl::from_args(&[program_name], &arg_strs[1..]) {
+        Ok(command) => command,
+        Err(early_exit) => {
+            // `argh` only knows the built-in subcommands, so user-defined commands
+            // are looked up once parsing fails.
+            if let (Err(()), Some(name)) = (early_exit.status, args.get(1)) {
+                if !name.starts_with('-') {
+                    let config = load_config();
+                    if let Some(script) = config.config.commands.get(name) {
+                        cmd::external::external(&config, script, &args[2..]);
+                    }
+                }
+            }
+            match early_exit.status {
+                Ok(()) => {
+                    println!("{}", early_exit.output);
+                    std::process::exit(0);
+                }
+                Err(()) => {
+                    eprintln!("{}", early_exit.output);
+                    std::process::exit(1);
+                }
+            }
+        }
     };
 
+    let mut config = load_config();
+
+    let profile = resolve_profile(&config, command.profile);
+    // Without an override, `colored` already leaves the output uncolored when
+    // $NO_COLOR is set or stdout is not a terminal.
+    if let Some(color) = profile.color {
+        colored::control::set_override(color);
+    }
+    if command.no_color {
+        colored::control::set_override(false);
+    }
+    if let Some(template_dir) = &profile.template_dir {
+        config.template_dir = Some(expand_profile_path(template_dir));
+    }
+    // The environment, being given per invocation, takes precedence over the profile.
+    if let Some(template_dir) = std::env::var("BOYL_TEMPLATE_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+    {
+        match template_dir.parse::<userpath::UserPath>() {
+            Ok(path) => config.template_dir = Some(path.path_buf),
+            Err(err) => {
+                eprintln!("{}", "Error in BOYL_TEMPLATE_DIR:".red());
+                eprintln!("{}", err.to_string().red());
+                std::process::exit(exitcode::CONFIG);
+            }
+        }
+    }
+
     match command.command {
-        Command::List(_) => cmd::list::list(&config),
-        Command::Tree(tree) => cmd::tree::tree(&config, &tree.template),
+        Command::List(list) => cmd::list::list(
+            &config,
+            list.json,
+            list.tag.as_deref(),
+            list.stats,
+            list.sort.unwrap_or_default(),
+            &list.time_format.unwrap_or_default(),
+        ),
+        Command::Tree(tree) => match tree.diff {
+            Some(other) => cmd::tree::diff(&config, &other, &tree.template, tree.content),
+            None => cmd::tree::tree(
+                &config,
+                &tree.template,
+                tree.only_text,
+                tree.plain,
+                tree.depth,
+            ),
+        },
         Command::Make(make) => {
-            cmd::make::make(
+            let made = cmd::make::make(
                 &mut config,
-                make.name,
-                make.location.map(|d| d.path_buf).unwrap_or_else(|| {
-                    std::env::current_dir().expect("Could not determine current directory.")
-                }),
-                make.description,
-                make.all,
+                make_template_name(make.name, make.named),
+                make.location
+                    .map(|d| d.path_buf)
+                    .unwrap_or_else(userpath::current_dir_or_exit),
+                cmd::make::MakeOptions {
+                    description: make.description,
+                    license: make.license,
+                    template_version: make.template_version,
+                    all: make.all,
+                    json: make.json,
+                    includes: make.include,
+                    exclude_binary: make.exclude_binary,
+                    patterns_from: make.patterns_from,
+                    no_gitignore: make.no_gitignore,
+                    jobs: make.jobs.or(profile.jobs),
+                    follow_symlinks: make.follow_symlinks,
+                    preserve: make.preserve,
+                    strict: make.strict,
+                    verbosity: make.verbose.into(),
+                    quiet: make.quiet,
+                    tags: make.tag,
+                },
             );
+            let complete = match made {
+                Ok(complete) => complete,
+                Err(err) => fail(&err, err.hint(), err.exit_code()),
+            };
             config::write_config_or_fail(&config);
+            if !complete {
+                std::process::exit(exitcode::IOERR);
+            }
         }
         Command::New(new) => {
-            cmd::new::new(&config, &new.template, new.name.as_deref(), new.location)
+            let created = cmd::new::new(
+                &mut config,
+                new.template.as_deref(),
+                cmd::new::NewOptions {
+                    name: new.name,
+                    location: new.location.or_else(|| {
+                        profile
+                            .location
+                            .as_deref()
+                            .map(|location| userpath::UserPath {
+                                path_buf: expand_profile_path(location),
+                            })
+                    }),
+                    author: new.author.or(profile.author),
+                    parents: new.parents,
+                    prune_empty: new.prune_empty,
+                    set_executable: new.set_executable,
+                    dry_run: new.dry_run,
+                    force: new.force,
+                    overwrite_if_newer: new.overwrite_if_newer,
+                    no_hooks: new.no_hooks,
+                    hooks_env: new.hooks_env,
+                    from_git: new.from_git,
+                    from_stdin_archive: new.from_stdin_archive,
+                    jobs: new.jobs.or(profile.jobs),
+                    follow_symlinks: new.follow_symlinks,
+                    preserve: new.preserve,
+                    strict: new.strict,
+                    verbosity: new.verbose.into(),
+                    quiet: new.quiet,
+                    print_path: new.print_path,
+                },
+            );
+            match created {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(exitcode::IOERR),
+                Err(err) => fail(&err, err.hint(), err.exit_code()),
+            }
         }
         Command::Edit(_) => {
             cmd::edit::edit(&mut config);
+        }
+        Command::Alias(alias) => {
+            match alias.action {
+                AliasAction::Add(add) => cmd::alias::add(&mut config, &add.template, add.alias),
+                AliasAction::Remove(remove) => cmd::alias::remove(&mut config, &remove.alias),
+            }
+            config::write_config_or_fail(&config);
+        }
+        Command::Rename(rename) => {
+            cmd::rename::rename(&mut config, &rename.old, &rename.new);
             config::write_config_or_fail(&config);
         }
+        Command::Describe(describe) => {
+            cmd::describe::describe(&mut config, &describe.template, &describe.description);
+            config::write_config_or_fail(&config);
+        }
+        Command::Open(open) => cmd::open::open(&config, &open.template, open.editor),
+        Command::Export(export) => cmd::export::export(&config, &export.template, &export.out),
+        Command::Import(import) => {
+            cmd::import::import(&mut config, &import.archive);
+            config::write_config_or_fail(&config);
+        }
+        Command::Config(command) => {
+            match command.action {
+                ConfigAction::SetDefaultLocation(set) => {
+                    cmd::config::set_default_location(&mut config, set.location)
+                }
+            }
+            config::write_config_or_fail(&config);
+        }
+        Command::Doctor(doctor) => {
+            cmd::doctor::doctor(&mut config, doctor.fix);
+            if doctor.fix {
+                config::write_config_or_fail(&config);
+            }
+        }
         Command::Xoxo(_) => cmd::xoxo::xoxo(),
-        Command::Version(_) => cmd::version::version(),
+        Command::Version(version) => cmd::version::version(&config, version.check),
     }
 
     std::process::exit(exitcode::OK)
@@ -0,0 +1,128 @@
+//! Upgrades of configuration files written by older versions of boyl.
+//!
+//! Migrations operate on the JSON of the configuration, before it is deserialized
+//! into a [`crate::config::Config`], so that they can deal with shapes that the
+//! current structs no longer accept.
+
+use crate::version::Version;
+use serde_json::Value;
+
+/// A migration, and the version of boyl from which configurations no longer need it.
+struct Migration {
+    /// Configurations written by versions before this one are migrated.
+    since: &'static str,
+    apply: fn(Value) -> Value,
+}
+
+/// The migrations, in the order they are applied.
+const MIGRATIONS: &[Migration] = &[Migration {
+    since: "0.2.0",
+    apply: rename_template_path,
+}];
+
+/// Upgrades a configuration written by version `from` of boyl to the shape of the
+/// current version, and marks it as written by the current version.
+///
+/// Versions that cannot be parsed are taken to be older than any migration.
+pub fn migrate(from: &str, mut config: Value) -> Value {
+    let from = Version::parse(from);
+    for migration in MIGRATIONS {
+        let since = Version::parse(migration.since).expect("Migrations have valid versions.");
+        if from.is_none_or(|from| from < since) {
+            config = (migration.apply)(config);
+        }
+    }
+    if let Some(object) = config.as_object_mut() {
+        object.insert("version".to_string(), Value::from(crate::VERSION));
+    }
+    config
+}
+
+/// Templates used to store their directory under `directory_name` or `directory`,
+/// rather than `path`.
+fn rename_template_path(mut config: Value) -> Value {
+    let templates = match config.get_mut("templates").and_then(Value::as_object_mut) {
+        Some(templates) => templates,
+        None => return config,
+    };
+    for template in templates.values_mut().filter_map(Value::as_object_mut) {
+        if template.contains_key("path") {
+            continue;
+        }
+        let old = template
+            .remove("directory_name")
+            .or_else(|| template.remove("directory"));
+        if let Some(path) = old {
+            template.insert("path".to_string(), path);
+        }
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, LoadedConfig};
+
+    /// A configuration as written by boyl 0.1.0, with the templates keyed by the
+    /// standard library's hash of their names, and their directories under older
+    /// field names.
+    const FIXTURE_0_1_0: &str = r#"{
+        "version": "0.1.0",
+        "templates": {
+            "1111": {
+                "name": "rust",
+                "description": "A Rust project",
+                "directory_name": "/templates/rust"
+            },
+            "2222": {
+                "name": "web",
+                "description": null,
+                "directory": "/templates/web"
+            }
+        }
+    }"#;
+
+    #[test]
+    fn old_template_paths_are_renamed() {
+        let old = serde_json::from_str::<Value>(FIXTURE_0_1_0).unwrap();
+        let migrated = migrate("0.1.0", old);
+        assert_eq!(migrated["version"], crate::VERSION);
+        assert_eq!(migrated["templates"]["1111"]["path"], "/templates/rust");
+        assert_eq!(migrated["templates"]["2222"]["path"], "/templates/web");
+        assert!(migrated["templates"]["1111"]
+            .get("directory_name")
+            .is_none());
+    }
+
+    #[test]
+    fn current_configurations_are_left_alone() {
+        let current = serde_json::json!({
+            "version": "0.2.0",
+            "templates": { "1": { "name": "a", "path": "/a", "directory": "/b" } }
+        });
+        assert_eq!(
+            migrate("0.2.0", current.clone())["templates"],
+            current["templates"]
+        );
+    }
+
+    #[test]
+    fn old_configuration_files_load() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("config.json"), FIXTURE_0_1_0).unwrap();
+        let loaded = match LoadedConfig::load_from_path(dir.path().to_path_buf()) {
+            Ok(loaded) => loaded,
+            Err(err) => panic!("{}", err),
+        };
+        let config = loaded.config;
+        assert_eq!(config.written_by.as_deref(), Some("0.1.0"));
+        assert_eq!(config.version, crate::VERSION);
+
+        let (key, rust) = config.find_template("rust").unwrap();
+        assert_eq!(key, Config::get_template_key("rust"));
+        assert_eq!(rust.path, std::path::PathBuf::from("/templates/rust"));
+        let (_, web) = config.find_template("web").unwrap();
+        assert_eq!(web.path, std::path::PathBuf::from("/templates/web"));
+    }
+}
@@ -0,0 +1,106 @@
+use colored::Colorize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Replaces the `{{key}}` placeholders in `text` with the corresponding values.
+pub fn substitute(text: &str, variables: &BTreeMap<String, String>) -> String {
+    variables
+        .iter()
+        .fold(text.to_string(), |text, (key, value)| {
+            text.replace(&format!("{{{{{}}}}}", key), value)
+        })
+}
+
+/// Every file and directory under `base` (excluding `base`), parents before
+/// their children. Directories that cannot be read are skipped.
+fn collect_tree(base: &Path) -> Vec<PathBuf> {
+    let mut found = vec![];
+    let mut to_visit = vec![base.to_path_buf()];
+    while let Some(dir) = to_visit.pop() {
+        let entries = match dir.read_dir() {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn(&dir, err);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                to_visit.push(path.clone());
+            }
+            found.push(path);
+        }
+    }
+    found
+}
+
+fn warn(path: &Path, err: impl std::fmt::Display) {
+    eprintln!(
+        "{}",
+        format!(
+            "Could not substitute variables in {}, with error: {}",
+            path.to_string_lossy(),
+            err
+        )
+        .yellow()
+    );
+}
+
+/// Replaces the `{{key}}` placeholders in the contents and names of every file
+/// (and directory) under `base`.
+///
+/// Files that look binary are left untouched. Failing to substitute in one file
+/// is not fatal; a warning is printed, and the remaining files are processed.
+pub fn substitute_tree(base: &Path, variables: &BTreeMap<String, String>) {
+    let tree = collect_tree(base);
+
+    // Symbolic links are skipped, so as not to write to what they point to.
+    let is_regular_file = |path: &&PathBuf| {
+        path.symlink_metadata()
+            .map(|metadata| metadata.is_file())
+            .unwrap_or(false)
+    };
+    for path in tree.iter().filter(is_regular_file) {
+        if crate::binary::is_probably_binary(path) {
+            continue;
+        }
+        // Rewritten files keep their modification times, which may have been
+        // preserved from the template.
+        let result = std::fs::read_to_string(path).and_then(|text| {
+            let substituted = substitute(&text, variables);
+            if substituted != text {
+                let mtime = filetime::FileTime::from_last_modification_time(&path.metadata()?);
+                std::fs::write(path, substituted)?;
+                filetime::set_file_mtime(path, mtime)
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(err) = result {
+            warn(path, err);
+        }
+    }
+
+    // Children are renamed before their parents, so that their paths stay valid.
+    for path in tree.iter().rev() {
+        let file_name = match path.file_name() {
+            Some(file_name) => file_name.to_string_lossy(),
+            None => continue,
+        };
+        let substituted = substitute(&file_name, variables);
+        if substituted == file_name {
+            continue;
+        }
+        let target = path.with_file_name(substituted);
+        if target.exists() {
+            warn(path, format!("{} already exists", target.to_string_lossy()));
+            continue;
+        }
+        if let Err(err) = std::fs::rename(path, &target) {
+            warn(path, err);
+        }
+    }
+}
@@ -1,8 +1,84 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Template {
     pub name: String,
     pub description: Option<String>,
+    /// Where the template's files are stored. (Older configurations, which call
+    /// this `directory_name` or `directory`, are migrated when loaded.)
     pub path: PathBuf,
-}
\ No newline at end of file
+    /// SPDX identifier of the license to stamp onto new instances of the template.
+    #[serde(default)]
+    pub license: Option<String>,
+    /// Shortcut names for the template, which can be used wherever its name is
+    /// expected. No two templates share an alias, nor is an alias the name of a
+    /// template.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// User-defined version of the template, to keep track of its evolution.
+    #[serde(default)]
+    pub template_version: Option<String>,
+    /// Names of other templates to lay down, in order, before this template's
+    /// own files, when creating a new project.
+    #[serde(default)]
+    pub includes: Vec<String>,
+    /// Position of the template when listed; ties are broken arbitrarily.
+    #[serde(default)]
+    pub order: u32,
+    /// Values for `{{key}}` placeholders in the files (and file names) of new
+    /// projects, on top of the built-in `name`, `date` and `year`.
+    #[serde(default)]
+    pub substitutions: BTreeMap<String, String>,
+    /// Placeholders whose values are asked for when creating a new project.
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    /// Exclusion patterns chosen in the file picker when the template was made.
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    /// Shell command to run in new projects once they are created (e.g. `git init`),
+    /// with the same `{{key}}` placeholders as the files.
+    #[serde(default)]
+    pub post_create: Option<String>,
+    /// Free-form labels to group templates by, as filtered with `list --tag`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// When the template was made, as an RFC 3339 timestamp. Templates made by
+    /// older versions of boyl have none.
+    #[serde(default)]
+    pub created_at: Option<String>,
+    /// When a project was last created from the template, as an RFC 3339
+    /// timestamp, if ever.
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    /// How many projects have been created from the template.
+    #[serde(default)]
+    pub use_count: u32,
+    /// Whether the template was found in one of the extra template directories,
+    /// rather than registered in the configuration. Such templates are not saved.
+    #[serde(skip)]
+    pub external: bool,
+}
+
+/// Description of a template stored alongside its files in an exported archive,
+/// leaving out what only makes sense on the exporting machine (e.g. its path).
+#[derive(Serialize, Deserialize)]
+pub struct TemplateManifest {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub template_version: Option<String>,
+}
+
+/// A `{{key}}` placeholder whose value is asked for interactively.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TemplateVariable {
+    pub key: String,
+    /// The question to ask; the key is shown if there is none.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Value to use if the answer is left empty.
+    #[serde(default)]
+    pub default: Option<String>,
+}
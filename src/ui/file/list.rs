@@ -1,13 +1,19 @@
 use parking_lot::RwLock;
 use std::{
-    cmp::min,
+    cmp::{max, min},
     collections::{BTreeSet, HashMap},
+    fmt::Display,
     ops::Range,
     path::{Path, PathBuf},
     sync::Arc,
 };
 use uuid::Uuid;
 
+/// `expand_all` stops opening directories once the list is this long, so that
+/// expanding a huge tree does not index the whole disk. Likewise, `filtered_view`
+/// looks at no more than this many entries.
+const EXPAND_ALL_LIMIT: usize = 10_000;
+
 /// Entry in the [`FileList`].
 struct FileListItem {
     /// The UUID of the `FileListItem` corresponding to the parent directory
@@ -32,41 +38,97 @@ pub struct FileList<'path> {
     /// Files as they are displayed in the file list, as represented by their UUID
     /// keys.
     file_list: Vec<Uuid>,
-    /// If a UUID is contained in this set, then its contents have been indexed
-    /// previously, to at least one level of depth, and every direct child of this
-    /// file has a key in `file_keys`.
-    indexed: BTreeSet<Uuid>,
+    /// If a UUID is a key of this map, then its contents have been indexed
+    /// previously, to at least one level of depth, and the keys of its direct
+    /// children are mapped to, in the order they are shown.
+    indexed: HashMap<Uuid, Vec<Uuid>>,
     exclude_patterns: BTreeSet<glob::Pattern>,
+    /// Files matching these patterns are not excluded by `exclude_patterns`.
+    except_patterns: BTreeSet<glob::Pattern>,
     exclude_exceptions: BTreeSet<Uuid>,
     exclude_explicit: BTreeSet<Uuid>,
+    /// If not empty, only files (not directories) matching one of these patterns
+    /// are included, unless they are an exception.
+    include_patterns: BTreeSet<glob::Pattern>,
+    /// Whether to leave out files that look binary. These are classified once,
+    /// when their directory is indexed, and never enter the list.
+    only_text: bool,
     pub highlight: usize,
+    /// While the list is filtered by `filtered_view`, the unfiltered list and its
+    /// highlight, to go back to.
+    unfiltered: Option<(Vec<Uuid>, usize)>,
+    /// If set, how many levels of the tree can be shown; directories at the last
+    /// level cannot be opened.
+    max_depth: Option<usize>,
 }
 
 pub struct FileListIterElement<'path> {
     pub path: &'path Path,
     pub included: bool,
     pub depth: usize,
+    /// Whether this is a directory that cannot be opened, because of the depth limit.
+    pub depth_limited: bool,
+}
+
+/// Why a file is included or excluded, as given by [`FileList::why_included`].
+pub enum InclusionReason {
+    /// The file was included by hand, even though a pattern excludes it.
+    ExplicitException,
+    /// The file was excluded by hand.
+    ExplicitExclude,
+    /// The file matches this exclusion pattern, and no exception to it.
+    MatchedPattern(String),
+    /// There are include patterns, and the file matches none of them.
+    NotIncludedByPattern,
+    /// This directory (relative to the base path) is excluded, and so are its
+    /// contents.
+    ParentExcluded(PathBuf),
+    /// Nothing excludes the file.
+    DefaultIncluded,
+}
+
+impl Display for InclusionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InclusionReason::ExplicitException => {
+                write!(f, "included by hand, despite the patterns")
+            }
+            InclusionReason::ExplicitExclude => write!(f, "excluded by hand"),
+            InclusionReason::MatchedPattern(pattern) => {
+                write!(f, "matches the pattern {}", pattern)
+            }
+            InclusionReason::NotIncludedByPattern => write!(f, "matches no include pattern"),
+            InclusionReason::ParentExcluded(parent) => {
+                write!(f, "{} is excluded", parent.to_string_lossy())
+            }
+            InclusionReason::DefaultIncluded => write!(f, "nothing excludes it"),
+        }
+    }
 }
 
 impl<'path> FileList<'path> {
     pub fn new(base_path: &'path Path) -> Self {
+        FileList::new_filtered(base_path, false)
+    }
+
+    /// Like [`FileList::new`], but leaving out files that look binary if
+    /// `only_text` is set.
+    pub fn new_filtered(base_path: &'path Path, only_text: bool) -> Self {
         let mut file_items = HashMap::<Uuid, FileListItem>::new();
         let mut file_keys = HashMap::<PathBuf, Uuid>::new();
         let mut file_list = vec![];
-        for base_child in base_path
-            .read_dir()
-            .expect("Could not read base directory.")
-            .flatten()
+        for base_child in
+            read_children(base_path, only_text).expect("Could not read base directory.")
         {
             let key = Uuid::new_v4();
             let item = FileListItem {
                 parent: None,
                 open: false,
-                path: base_child.path(),
+                path: base_child.clone(),
                 depth: 0,
             };
             file_items.insert(key, item);
-            file_keys.insert(base_child.path(), key);
+            file_keys.insert(base_child, key);
             file_list.push(key);
         }
 
@@ -75,14 +137,25 @@ impl<'path> FileList<'path> {
             file_items,
             file_keys,
             file_list,
-            indexed: BTreeSet::<Uuid>::new(),
+            indexed: HashMap::<Uuid, Vec<Uuid>>::new(),
             exclude_patterns: BTreeSet::<glob::Pattern>::new(),
+            except_patterns: BTreeSet::<glob::Pattern>::new(),
             exclude_exceptions: BTreeSet::<Uuid>::new(),
             exclude_explicit: BTreeSet::<Uuid>::new(),
+            include_patterns: BTreeSet::<glob::Pattern>::new(),
+            only_text,
             highlight: 0,
+            unfiltered: None,
+            max_depth: None,
         }
     }
 
+    /// Shows no more than `max_depth` levels of the tree (with `1` showing only the
+    /// entries of the base directory).
+    pub fn limit_depth(&mut self, max_depth: usize) {
+        self.max_depth = Some(max_depth);
+    }
+
     pub fn go_up(&mut self) {
         self.highlight = self.highlight.saturating_sub(1);
     }
@@ -94,11 +167,33 @@ impl<'path> FileList<'path> {
         );
     }
 
+    pub fn go_page_up(&mut self, height: usize) {
+        self.highlight = self.highlight.saturating_sub(max(height, 1));
+    }
+
+    pub fn go_page_down(&mut self, height: usize) {
+        self.highlight = min(
+            self.highlight.saturating_add(max(height, 1)),
+            self.file_list.len().saturating_sub(1),
+        );
+    }
+
+    pub fn go_first(&mut self) {
+        self.highlight = 0;
+    }
+
+    pub fn go_last(&mut self) {
+        self.highlight = self.file_list.len().saturating_sub(1);
+    }
+
     pub fn toggle_folder(&mut self) {
         if self.file_list.is_empty() {
             return;
         }
         let file_key = self.file_list[self.highlight];
+        if self.is_depth_limited(&file_key) {
+            return;
+        }
         let file = self.file_items.get_mut(&file_key).unwrap();
         if !file.path.is_dir() {
             return;
@@ -110,6 +205,164 @@ impl<'path> FileList<'path> {
         }
     }
 
+    /// Opens every directory in the list, and every directory inside those, until
+    /// the whole tree is shown (or the list reaches `EXPAND_ALL_LIMIT` entries).
+    /// Symbolic links to directories are not followed, as they may form cycles.
+    pub fn expand_all(&mut self) {
+        let highlighted = self.file_list.get(self.highlight).copied();
+        let mut index = 0;
+        while index < self.file_list.len() && self.file_list.len() < EXPAND_ALL_LIMIT {
+            let key = self.file_list[index];
+            let depth_limited = self.is_depth_limited(&key);
+            let file = self.file_items.get_mut(&key).unwrap();
+            if is_real_dir(&file.path) && !file.open && !depth_limited {
+                file.open = true;
+                self.expand_dir(index);
+            }
+            index += 1;
+        }
+        self.highlight_key(highlighted);
+    }
+
+    /// Closes every directory, leaving only the top-level entries in the list.
+    pub fn collapse_all(&mut self) {
+        // The highlight moves to the top-level entry that contains it.
+        let mut highlighted = self.file_list.get(self.highlight).copied();
+        while let Some(parent) =
+            highlighted.and_then(|key| self.file_items.get(&key).unwrap().parent)
+        {
+            highlighted = Some(parent);
+        }
+        for file in self.file_items.values_mut() {
+            file.open = false;
+        }
+        let file_items = &self.file_items;
+        self.file_list
+            .retain(|key| file_items.get(key).unwrap().parent.is_none());
+        self.highlight_key(highlighted);
+    }
+
+    /// Shows only the entries whose names contain `query` (ignoring case), along with
+    /// the directories that contain them, whether they were open or not. The highlight
+    /// goes to the first match.
+    ///
+    /// The list can then be brought back with `cancel_filter`, or with `accept_filter`;
+    /// an empty `query` shows the unfiltered list.
+    pub fn filtered_view(&mut self, query: &str) {
+        if self.unfiltered.is_none() {
+            self.unfiltered = Some((self.file_list.clone(), self.highlight));
+        }
+        let (unfiltered, unfiltered_highlight) = self.unfiltered.clone().unwrap();
+        if query.is_empty() {
+            self.file_list = unfiltered;
+            self.highlight = unfiltered_highlight;
+            return;
+        }
+
+        let query = query.to_lowercase();
+        let top_level = unfiltered
+            .into_iter()
+            .filter(|key| self.file_items.get(key).unwrap().parent.is_none())
+            .collect::<Vec<Uuid>>();
+        let mut view = vec![];
+        let mut first_match = None;
+        let mut budget = EXPAND_ALL_LIMIT;
+        for key in top_level {
+            self.filter_subtree(key, &query, &mut view, &mut first_match, &mut budget);
+        }
+        self.file_list = view;
+        self.highlight = first_match.unwrap_or(0);
+    }
+
+    /// Goes back to the list as it was before `filtered_view`.
+    pub fn cancel_filter(&mut self) {
+        if let Some((unfiltered, highlight)) = self.unfiltered.take() {
+            self.file_list = unfiltered;
+            self.highlight = highlight;
+        }
+    }
+
+    /// Goes back to the list as it was before `filtered_view`, but with the
+    /// directories containing the highlighted match opened, and the match highlighted.
+    pub fn accept_filter(&mut self) {
+        let highlighted = self.file_list.get(self.highlight).copied();
+        self.cancel_filter();
+        let highlighted = match highlighted {
+            Some(key) => key,
+            None => return,
+        };
+
+        let mut ancestors = vec![];
+        let mut parent = self.file_items.get(&highlighted).unwrap().parent;
+        while let Some(key) = parent {
+            ancestors.push(key);
+            parent = self.file_items.get(&key).unwrap().parent;
+        }
+        // Outermost first, so that each directory is in the list when it is opened.
+        for key in ancestors.into_iter().rev() {
+            let index = match self.file_list.iter().position(|&id| id == key) {
+                Some(index) => index,
+                None => break,
+            };
+            let file = self.file_items.get_mut(&key).unwrap();
+            if !file.open {
+                file.open = true;
+                self.expand_dir(index);
+            }
+        }
+        self.highlight_key(Some(highlighted));
+    }
+
+    /// Pushes `key` to `view`, followed by its descendants that match `query` (and
+    /// the directories leading to them), if it matches or has such descendants.
+    /// Returns whether it was pushed.
+    fn filter_subtree(
+        &mut self,
+        key: Uuid,
+        query: &str,
+        view: &mut Vec<Uuid>,
+        first_match: &mut Option<usize>,
+        budget: &mut usize,
+    ) -> bool {
+        if *budget == 0 {
+            return false;
+        }
+        *budget -= 1;
+
+        let position = view.len();
+        view.push(key);
+        let path = self.file_items.get(&key).unwrap().path.clone();
+        let name_matches = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase().contains(query))
+            .unwrap_or(false);
+        if name_matches && first_match.is_none() {
+            *first_match = Some(position);
+        }
+
+        let mut has_matches = false;
+        if is_real_dir(&path) && !self.is_depth_limited(&key) {
+            if !self.indexed.contains_key(&key) {
+                self.index_dir(&key);
+            }
+            for child in self.child_keys(&key) {
+                has_matches |= self.filter_subtree(child, query, view, first_match, budget);
+            }
+        }
+
+        if !(name_matches || has_matches) {
+            view.truncate(position);
+        }
+        name_matches || has_matches
+    }
+
+    /// Moves the highlight to the entry of the given key, if it is in the list.
+    fn highlight_key(&mut self, key: Option<Uuid>) {
+        let position = key.and_then(|key| self.file_list.iter().position(|&id| id == key));
+        self.highlight =
+            position.unwrap_or_else(|| min(self.highlight, self.file_list.len().saturating_sub(1)));
+    }
+
     pub fn toggle_exclude_file(&mut self) {
         let file_key = self.file_list[self.highlight];
 
@@ -136,8 +389,9 @@ impl<'path> FileList<'path> {
         };
     }
 
+    /// Excludes the files that match `pattern` (see [`normalize_pattern`]).
     pub fn exclude_pattern(&mut self, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let pattern = glob::Pattern::new(pattern)?;
+        let pattern = glob::Pattern::new(&normalize_pattern(pattern))?;
         // New ignore pattern was newly inserted, so any exceptions that match the rule are
         // no longer exceptions.
         // NOTE: This double iteration seems unavoidable, because `drain_filter` is not stabilized.
@@ -165,6 +419,27 @@ impl<'path> FileList<'path> {
         Ok(())
     }
 
+    /// Adds an exception to the exclusion patterns: files that match `pattern` are
+    /// not excluded by any pattern (but can still be excluded explicitly).
+    pub fn except_pattern(&mut self, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let pattern = glob::Pattern::new(&normalize_pattern(pattern))?;
+        self.except_patterns.insert(pattern);
+        Ok(())
+    }
+
+    /// Adds a pattern to the allowlist; once there is any such pattern, only the
+    /// files that match one of them are included.
+    pub fn include_pattern(&mut self, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let pattern = glob::Pattern::new(&normalize_pattern(pattern))?;
+        self.include_patterns.insert(pattern);
+        Ok(())
+    }
+
+    /// The exclusion patterns, as given to [`FileList::exclude_pattern`].
+    pub fn exclude_patterns(&self) -> impl Iterator<Item = &str> + '_ {
+        self.exclude_patterns.iter().map(glob::Pattern::as_str)
+    }
+
     pub fn iter_paths(
         &self,
         range: Range<usize>,
@@ -178,10 +453,26 @@ impl<'path> FileList<'path> {
                     path,
                     included: self.is_id_included(id),
                     depth: item.depth,
+                    depth_limited: self.is_depth_limited(id),
                 }
             })
     }
 
+    /// Whether the highlighted entry is a directory (and so can be opened or closed).
+    pub fn is_highlight_dir(&self) -> bool {
+        self.file_list
+            .get(self.highlight)
+            .map(|key| self.file_items.get(key).unwrap().path.is_dir())
+            .unwrap_or(false)
+    }
+
+    /// The full path of the highlighted entry, if there are any entries.
+    pub fn highlight_path(&self) -> Option<&Path> {
+        self.file_list
+            .get(self.highlight)
+            .map(|key| self.file_items.get(key).unwrap().path.as_path())
+    }
+
     pub fn len(&self) -> usize {
         self.file_list.len()
     }
@@ -194,11 +485,15 @@ impl<'path> FileList<'path> {
     /// function provides memoization for the procedure, where answers are stored in
     /// `memo`.
     ///
+    /// Whether `path` is a directory should be given in `is_dir`, so that callers
+    /// that already know it spare a query to the file system.
+    ///
     /// This function expects the provided path to be a subpath of `self.base_path`.
     /// If this is not the case, behaviour is undefined.
     pub fn is_included_memoized_async(
         &self,
         path: &Path,
+        is_dir: bool,
         memo: Arc<RwLock<HashMap<PathBuf, bool>>>,
     ) -> bool {
         if let Some(answer) = {
@@ -209,23 +504,85 @@ impl<'path> FileList<'path> {
         } {
             return answer;
         }
-        let answer = if let Some(id) = self.file_keys.get(path) {
+        let answer = self.is_path_included_given(path, is_dir, |parent| {
+            self.is_included_memoized_async(parent, true, memo.clone())
+        });
+        if is_dir {
+            let mut lock = memo.write();
+            (*lock).insert(path.into(), answer);
+            drop(lock);
+        }
+        answer
+    }
+
+    /// Whether a path is to be included, per the settings of the user. This is
+    /// [`FileList::is_included_memoized_async`] without the memoization (and so
+    /// without side effects), querying the file system for whether `path` is a
+    /// directory.
+    ///
+    /// This function expects the provided path to be a subpath of `self.base_path`.
+    pub fn is_path_included(&self, path: &Path) -> bool {
+        self.is_path_included_given(path, path.is_dir(), |parent| self.is_path_included(parent))
+    }
+
+    /// Whether `path` is to be included, where `parent_included` tells whether a
+    /// parent directory is (for paths that have not been indexed).
+    fn is_path_included_given(
+        &self,
+        path: &Path,
+        is_dir: bool,
+        parent_included: impl FnOnce(&Path) -> bool,
+    ) -> bool {
+        if let Some(id) = self.file_keys.get(path) {
             self.is_id_included(id)
         } else {
             // We have not seen this file. This may be because
             // it is in a subdirectory that was not enumerated.
-            self.is_included_memoized_async(
+            let relative_path = path.strip_prefix(self.base_path).unwrap();
+            parent_included(
                 path.parent()
                     .expect("Expected the file path to have a parent."),
-                memo.clone(),
-            )
-        };
-        if path.is_dir() {
-            let mut lock = memo.write();
-            (*lock).insert(path.into(), answer);
-            drop(lock);
+            ) && !self.pattern_excluded(relative_path)
+                && (is_dir || self.matches_include_patterns(relative_path))
+        }
+    }
+
+    /// Why `path` is included or not, following the same rules as
+    /// [`FileList::is_included_memoized_async`] (and with the same expectation that
+    /// `path` is a subpath of `self.base_path`).
+    pub fn why_included(&self, path: &Path) -> InclusionReason {
+        if path == self.base_path {
+            return InclusionReason::DefaultIncluded;
+        }
+        let key = self.file_keys.get(path);
+        if key.is_some_and(|key| self.exclude_exceptions.contains(key)) {
+            return InclusionReason::ExplicitException;
+        }
+        if self.is_path_included(path) {
+            return InclusionReason::DefaultIncluded;
+        }
+
+        // Otherwise, find what excludes it.
+        let relative_path = path.strip_prefix(self.base_path).unwrap();
+        if key.is_some_and(|key| self.exclude_explicit.contains(key)) {
+            return InclusionReason::ExplicitExclude;
+        }
+        if let Some(pattern) = self.excluding_pattern(relative_path) {
+            return InclusionReason::MatchedPattern(pattern.as_str().to_string());
+        }
+        if !path.is_dir() && !self.matches_include_patterns(relative_path) {
+            return InclusionReason::NotIncludedByPattern;
+        }
+        // A file can be excluded because a parent is excluded.
+        let parent = path
+            .parent()
+            .expect("Expected the file path to have a parent.");
+        match self.why_included(parent) {
+            InclusionReason::ParentExcluded(excluded) => InclusionReason::ParentExcluded(excluded),
+            _ => InclusionReason::ParentExcluded(
+                parent.strip_prefix(self.base_path).unwrap().to_path_buf(),
+            ),
         }
-        answer
     }
 
     fn is_id_included(&self, uuid: &Uuid) -> bool {
@@ -235,14 +592,25 @@ impl<'path> FileList<'path> {
         }
 
         let self_excluded = self.exclude_explicit.contains(uuid)
-            || self
-                .exclude_patterns
-                .iter()
-                .any(|pattern| self.exclusion_pattern_matches(pattern, uuid));
+            || self.pattern_excluded(
+                self.file_items
+                    .get(uuid)
+                    .unwrap()
+                    .path
+                    .strip_prefix(self.base_path)
+                    .unwrap(),
+            );
         if self_excluded {
             return false;
         }
 
+        let item = self.file_items.get(uuid).unwrap();
+        if !item.path.is_dir()
+            && !self.matches_include_patterns(item.path.strip_prefix(self.base_path).unwrap())
+        {
+            return false;
+        }
+
         // A file can be excluded because a parent is excluded.
         if let Some(parent) = self.file_items.get(uuid).unwrap().parent {
             return self.is_id_included(&parent);
@@ -260,27 +628,25 @@ impl<'path> FileList<'path> {
     fn expand_dir(&mut self, index_in_list: usize) {
         let expand_file_key = self.file_list[index_in_list];
 
-        if !self.indexed.contains(&expand_file_key) {
+        if !self.indexed.contains_key(&expand_file_key) {
             self.index_dir(&expand_file_key);
         }
 
-        let expand_file = self.file_items.get(&expand_file_key).unwrap();
-        for child_path in expand_file
-            .path
-            .read_dir()
-            .expect("Could not read directory.")
-            .flatten()
-        {
-            let child_path = child_path.path();
-            let child_key = *self.file_keys.get(&child_path).unwrap();
-            self.file_list.insert(index_in_list + 1, child_key);
-        }
+        let children = self.child_keys(&expand_file_key);
+        self.file_list
+            .splice((index_in_list + 1)..(index_in_list + 1), children);
+    }
+
+    /// The keys of the children of an indexed directory, in the order they are shown.
+    fn child_keys(&self, dir_key: &Uuid) -> Vec<Uuid> {
+        self.indexed.get(dir_key).cloned().unwrap_or_default()
     }
 
-    /// Removes all elements immediately following the indicated element in the `file_list`,
-    /// until the `next_sibling` of the `FileListItem` is found, or until de end of the list,
-    /// if `next_sibling` is `None`. This has the effect of collapsing the subtree corresponding
-    /// to this file in the file list display.
+    /// Removes all elements immediately following the indicated element in the `file_list`
+    /// that are deeper in the tree than it, until the next sibling (or shallower file) is found,
+    /// or until the end of the list. This has the effect of collapsing the subtree corresponding
+    /// to this file in the file list display. Directories in the subtree are closed as well, so
+    /// that they are shown closed when the subtree is expanded again.
     ///
     /// This function expects the indicated element of the `file_list` to be a directory, and
     /// has undefined behaviour otherwise.
@@ -291,53 +657,291 @@ impl<'path> FileList<'path> {
         }
 
         let contract_file_key = self.file_list[index_in_list];
+        let contract_depth = self.file_items.get(&contract_file_key).unwrap().depth;
         let to_remove = self.file_list[(index_in_list + 1)..]
             .iter()
-            .take_while(|&id| {
-                self.file_items
-                    .get(id)
-                    .unwrap()
-                    .parent
-                    .map_or(false, |pid| pid == contract_file_key)
-            })
+            .take_while(|&id| self.file_items.get(id).unwrap().depth > contract_depth)
             .count();
-        self.file_list
-            .drain((index_in_list + 1)..(index_in_list + 1 + to_remove));
+        for id in self
+            .file_list
+            .drain((index_in_list + 1)..(index_in_list + 1 + to_remove))
+        {
+            self.file_items.get_mut(&id).unwrap().open = false;
+        }
     }
 
     fn index_dir(&mut self, file_key: &Uuid) {
         let file_item = self.file_items.get(file_key).unwrap();
 
         let child_depth = file_item.depth + 1;
-        for child_dir in file_item
-            .path
-            .read_dir()
-            .expect("Could not read directory.")
-            .flatten()
-        {
+        // A directory that cannot be read (e.g., removed since it was listed) is
+        // shown as empty.
+        let child_paths = read_children(&file_item.path, self.only_text).unwrap_or_default();
+        let mut child_keys = Vec::with_capacity(child_paths.len());
+        for child_path in child_paths {
             let key = Uuid::new_v4();
             let item = FileListItem {
                 parent: Some(*file_key),
                 open: false,
-                path: child_dir.path(),
+                path: child_path.clone(),
                 depth: child_depth,
             };
             self.file_items.insert(key, item);
-            self.file_keys.insert(child_dir.path(), key);
+            self.file_keys.insert(child_path, key);
+            child_keys.push(key);
+        }
+
+        self.indexed.insert(*file_key, child_keys);
+    }
+
+    /// Whether the entry is a directory whose contents would go past `max_depth`.
+    fn is_depth_limited(&self, key: &Uuid) -> bool {
+        let item = self.file_items.get(key).unwrap();
+        self.max_depth
+            .is_some_and(|max_depth| item.depth + 1 >= max_depth && item.path.is_dir())
+    }
+
+    /// Whether `relative_path` matches an exclusion pattern, and no exception to them.
+    fn pattern_excluded(&self, relative_path: &Path) -> bool {
+        self.excluding_pattern(relative_path).is_some()
+    }
+
+    /// The first exclusion pattern that `relative_path` matches, if it matches no
+    /// exception to them.
+    fn excluding_pattern(&self, relative_path: &Path) -> Option<&glob::Pattern> {
+        if self
+            .except_patterns
+            .iter()
+            .any(|pattern| pattern.matches_path_with(relative_path, MATCH_OPTIONS))
+        {
+            return None;
         }
+        self.exclude_patterns
+            .iter()
+            .find(|pattern| pattern.matches_path_with(relative_path, MATCH_OPTIONS))
+    }
 
-        self.indexed.insert(*file_key);
+    /// Whether `relative_path` is allowed by the include patterns (which is always the
+    /// case if there are none).
+    fn matches_include_patterns(&self, relative_path: &Path) -> bool {
+        self.include_patterns.is_empty()
+            || self
+                .include_patterns
+                .iter()
+                .any(|pattern| pattern.matches_path_with(relative_path, MATCH_OPTIONS))
     }
 
     fn exclusion_pattern_matches(&self, pattern: &glob::Pattern, id: &Uuid) -> bool {
-        pattern.matches_path(
-            &self
-                .file_items
+        pattern.matches_path_with(
+            self.file_items
                 .get(id)
                 .unwrap()
                 .path
                 .strip_prefix(self.base_path)
                 .unwrap(),
+            MATCH_OPTIONS,
         )
     }
 }
+
+/// How patterns are matched: as in a `.gitignore`, `*` and `?` do not match a
+/// `/`, so that `src/*.rs` does not reach into subdirectories of `src`.
+const MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Patterns are matched against paths relative to the base path. Like in a
+/// `.gitignore`, a pattern without any `/` (e.g., `*.log` or `target`) matches
+/// by name, at any depth, and so is made to start with `**/`; patterns with a
+/// `/` match the whole path.
+fn normalize_pattern(pattern: &str) -> String {
+    if pattern.contains('/') {
+        pattern.to_string()
+    } else {
+        format!("**/{}", pattern)
+    }
+}
+
+/// Kinds of entries in the list, in the order they are shown among siblings.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum EntryKind {
+    Directory,
+    File,
+}
+
+impl EntryKind {
+    fn of(path: &Path) -> Self {
+        if path.is_dir() {
+            EntryKind::Directory
+        } else {
+            EntryKind::File
+        }
+    }
+}
+
+/// Key by which siblings are ordered in the list: directories first, then files,
+/// each alphabetically (ignoring case, but falling back on it for names that
+/// differ only in case).
+///
+/// Finding the kind of an entry takes a `stat`, so the key is meant to be
+/// computed once per entry, with `sort_by_cached_key`.
+fn entry_sort_key(path: &Path) -> (EntryKind, String, String) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (EntryKind::of(path), name.to_lowercase(), name.into_owned())
+}
+
+/// The contents of the directory at `path`, in the order they are shown, leaving out
+/// files that look binary if `only_text` is set.
+pub fn read_children(path: &Path, only_text: bool) -> std::io::Result<Vec<PathBuf>> {
+    let mut children = path
+        .read_dir()?
+        .flatten()
+        .map(|child| child.path())
+        .filter(|child| !(only_text && is_binary_file(child)))
+        .collect::<Vec<PathBuf>>();
+    children.sort_by_cached_key(|child| entry_sort_key(child));
+    Ok(children)
+}
+
+/// Whether `path` is a directory, and not a symbolic link to one (which may form a cycle).
+pub fn is_real_dir(path: &Path) -> bool {
+    path.symlink_metadata()
+        .map(|metadata| metadata.is_dir())
+        .unwrap_or(false)
+}
+
+/// Whether `path` is a (non-directory) file that looks binary.
+fn is_binary_file(path: &Path) -> bool {
+    !path.is_dir() && crate::binary::is_probably_binary(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a small project tree in a temporary directory.
+    fn project() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in [
+            "README.md",
+            "notes.log",
+            "src/main.rs",
+            "src/lib.rs",
+            "src/gen/out.rs",
+            "logs/deep/trace.log",
+            "target/debug/app",
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+        dir
+    }
+
+    /// Moves the highlight of `list` to the entry at `path`, among those shown.
+    fn highlight(list: &mut FileList, path: &Path) {
+        list.go_first();
+        while list.highlight_path() != Some(path) {
+            list.go_down();
+        }
+    }
+
+    #[test]
+    fn everything_is_included_by_default() {
+        let dir = project();
+        let list = FileList::new(dir.path());
+        assert!(list.is_path_included(&dir.path().join("src/gen/out.rs")));
+        assert!(list.is_path_included(&dir.path().join("target/debug/app")));
+    }
+
+    #[test]
+    fn excluded_directories_exclude_their_contents() {
+        let dir = project();
+        let mut list = FileList::new(dir.path());
+        list.exclude_pattern("target").unwrap();
+        let target = dir.path().join("target");
+        assert!(!list.is_path_included(&target));
+        assert!(!list.is_path_included(&target.join("debug/app")));
+        assert!(matches!(
+            list.why_included(&target.join("debug/app")),
+            InclusionReason::ParentExcluded(parent) if parent == Path::new("target")
+        ));
+        assert!(list.is_path_included(&dir.path().join("src/main.rs")));
+    }
+
+    #[test]
+    fn exceptions_override_exclusion_patterns() {
+        let dir = project();
+        let mut list = FileList::new(dir.path());
+        list.exclude_pattern("*.rs").unwrap();
+        list.except_pattern("src/main.rs").unwrap();
+        assert!(list.is_path_included(&dir.path().join("src/main.rs")));
+        assert!(!list.is_path_included(&dir.path().join("src/lib.rs")));
+        assert!(matches!(
+            list.why_included(&dir.path().join("src/lib.rs")),
+            InclusionReason::MatchedPattern(pattern) if pattern == "**/*.rs"
+        ));
+    }
+
+    #[test]
+    fn include_patterns_keep_only_matching_files() {
+        let dir = project();
+        let mut list = FileList::new(dir.path());
+        list.include_pattern("*.md").unwrap();
+        assert!(list.is_path_included(&dir.path().join("README.md")));
+        assert!(!list.is_path_included(&dir.path().join("src/main.rs")));
+        // Directories are kept, as they may hold matching files.
+        assert!(list.is_path_included(&dir.path().join("src")));
+    }
+
+    #[test]
+    fn patterns_without_a_slash_match_at_any_depth() {
+        let dir = project();
+        let mut list = FileList::new(dir.path());
+        list.exclude_pattern("*.log").unwrap();
+        assert!(!list.is_path_included(&dir.path().join("notes.log")));
+        assert!(!list.is_path_included(&dir.path().join("logs/deep/trace.log")));
+        assert!(list.is_path_included(&dir.path().join("logs/deep")));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_match_the_whole_path() {
+        let dir = project();
+        let mut list = FileList::new(dir.path());
+        list.exclude_pattern("src/*.rs").unwrap();
+        assert!(!list.is_path_included(&dir.path().join("src/main.rs")));
+        assert!(list.is_path_included(&dir.path().join("src/gen/out.rs")));
+        list.exclude_pattern("src/**/*.rs").unwrap();
+        assert!(!list.is_path_included(&dir.path().join("src/gen/out.rs")));
+    }
+
+    #[test]
+    fn files_can_be_excluded_and_included_by_hand() {
+        let dir = project();
+        let readme = dir.path().join("README.md");
+        let mut list = FileList::new(dir.path());
+
+        highlight(&mut list, &readme);
+        list.toggle_exclude_file();
+        assert!(!list.is_path_included(&readme));
+        assert!(matches!(
+            list.why_included(&readme),
+            InclusionReason::ExplicitExclude
+        ));
+        list.toggle_exclude_file();
+        assert!(list.is_path_included(&readme));
+
+        // Including a file that a pattern excludes makes it an exception, until
+        // a pattern that matches it is added again.
+        list.exclude_pattern("*.md").unwrap();
+        list.toggle_exclude_file();
+        assert!(matches!(
+            list.why_included(&readme),
+            InclusionReason::ExplicitException
+        ));
+        list.exclude_pattern("README.md").unwrap();
+        assert!(!list.is_path_included(&readme));
+    }
+}
@@ -1,6 +1,6 @@
-use self::list::FileList;
+use self::{list::FileList, preview::FilePreview};
 use super::{
-    help,
+    help::{self, HelpText},
     input::{self, InputField},
 };
 use crate::ui::{
@@ -8,7 +8,7 @@ use crate::ui::{
     UiState, UiStateReaction,
 };
 use std::{cmp::min, path::Path};
-use termion::event::Key;
+use termion::event::{Key, MouseButton, MouseEvent};
 use tui::{
     backend::Backend,
     layout::Rect,
@@ -17,10 +17,12 @@ use tui::{
 };
 
 pub mod list;
+mod preview;
 
 #[derive(Clone, Copy)]
 enum InputMode {
     IgnorePattern,
+    IncludePattern,
 }
 
 #[derive(Clone)]
@@ -28,15 +30,88 @@ enum UiMode {
     List,
     Input(InputMode, InputField),
     Error(String),
+    Preview(FilePreview),
+    /// Searching the list, which is filtered as the query is typed.
+    Search(InputField),
 }
 
+#[derive(Default)]
 struct FileListWidget {
     buffer_start: usize,
+    /// Where the list was last drawn, to find the entries under the mouse.
+    area: Rect,
 }
 
-impl Default for FileListWidget {
-    fn default() -> Self {
-        FileListWidget { buffer_start: 0 }
+impl FileListWidget {
+    /// The index in the list of the entry drawn at the given (1-based, as given
+    /// by `termion`) terminal coordinates, if any.
+    fn entry_at(&self, x: u16, y: u16) -> Option<usize> {
+        let (x, y) = (x.checked_sub(1)?, y.checked_sub(1)?);
+        let area = self.area;
+        if x < area.left() || x >= area.right() || y < area.top() || y >= area.bottom() {
+            return None;
+        }
+        Some(self.buffer_start + (y - area.top()) as usize)
+    }
+
+    /// How many entries fit in the list, as of the last draw.
+    fn page_height(&self) -> usize {
+        self.area.height as usize
+    }
+}
+
+/// A preview of the highlighted entry, if it is a file.
+fn open_preview(file_list: &FileList) -> Option<FilePreview> {
+    let path = file_list.highlight_path()?;
+    if path.is_dir() {
+        return None;
+    }
+    let title = file_list
+        .iter_paths(file_list.highlight..file_list.highlight + 1)
+        .next()
+        .map(|elem| elem.path.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Some(FilePreview::load(path, title))
+}
+
+/// Handles a key press while previewing a file. Returns whether the preview
+/// should stay open.
+fn preview_input(preview: &mut FilePreview, key: Key) -> bool {
+    match key {
+        Key::Up | Key::Char('k') => preview.scroll_up(1),
+        Key::Down | Key::Char('j') => preview.scroll_down(1),
+        Key::PageUp => preview.scroll_page_up(),
+        Key::PageDown => preview.scroll_page_down(),
+        Key::Home => preview.scroll_to_top(),
+        Key::Esc | Key::Ctrl('c') | Key::Char('q') | Key::Char('p') => return false,
+        _ => {}
+    }
+    true
+}
+
+fn on_preview_mouse(preview: &mut FilePreview, event: MouseEvent) {
+    match event {
+        MouseEvent::Press(MouseButton::WheelUp, _, _) => preview.scroll_up(1),
+        MouseEvent::Press(MouseButton::WheelDown, _, _) => preview.scroll_down(1),
+        _ => {}
+    }
+}
+
+/// Handles a mouse event over a file list: clicking an entry highlights it (and
+/// opens or closes it, if it is a directory), and the wheel moves through the list.
+fn on_list_mouse(file_list: &mut FileList, file_widget: &FileListWidget, event: MouseEvent) {
+    match event {
+        MouseEvent::Press(MouseButton::Left, x, y) => {
+            if let Some(index) = file_widget.entry_at(x, y) {
+                if index < file_list.len() {
+                    file_list.highlight = index;
+                    file_list.toggle_folder();
+                }
+            }
+        }
+        MouseEvent::Press(MouseButton::WheelUp, _, _) => file_list.go_up(),
+        MouseEvent::Press(MouseButton::WheelDown, _, _) => file_list.go_down(),
+        _ => {}
     }
 }
 
@@ -52,25 +127,53 @@ impl<'path> FilePickerUi<'path> {
     pub fn new(base_path: &'path Path) -> Self {
         FilePickerUi {
             base_path,
-            file_list: FileList::new(&base_path),
+            file_list: FileList::new(base_path),
             file_widget: FileListWidget::default(),
             mode: UiMode::List,
             aborted: false,
         }
     }
 
+    /// Like [`FilePickerUi::new`], but starting with the given exclusion patterns.
+    /// Invalid patterns are ignored.
+    pub fn with_patterns(base_path: &'path Path, patterns: &[String]) -> Self {
+        let mut picker = FilePickerUi::new(base_path);
+        for pattern in patterns {
+            picker.file_list.exclude_pattern(pattern).ok();
+        }
+        picker
+    }
+
     fn draw_help(&self, f: &mut tui::Frame<impl Backend>, buffer_rect: Rect) -> Rect {
-        let (help_texts, help_boxes): (Vec<String>, Vec<VisualBox>) = vec![
-            super::help::make_help_box("Up/K", "Move up in list"),
-            super::help::make_help_box("Down/J", "Move down in list"),
-            super::help::make_help_box("O", "Open/Close folder"),
-            super::help::make_help_box("X", "Exclude/Include file"),
-            super::help::make_help_box("Z", "Exclude pattern"),
-            super::help::make_help_box("R", "Reset"),
-            super::help::make_help_box("Enter", "Finish"),
-        ]
-        .into_iter()
-        .unzip();
+        let mut helps = vec![];
+        if self.file_list.len() > 0 {
+            helps.extend(vec![
+                help::make_help_box("Up/K", "Move up in list"),
+                help::make_help_box("Down/J", "Move down in list"),
+                help::make_help_box("PgUp/PgDn", "Move a page"),
+                help::make_help_box("Home/End", "First/Last entry"),
+                help::make_help_box_enabled_if(
+                    self.file_list.is_highlight_dir(),
+                    "O",
+                    "Open/Close folder",
+                ),
+                help::make_help_box("Shift+E/C", "Expand/Collapse all"),
+                help::make_help_box_enabled_if(
+                    !self.file_list.is_highlight_dir(),
+                    "P",
+                    "Preview file",
+                ),
+                help::make_help_box("X", "Exclude/Include file"),
+            ]);
+        }
+        helps.extend(vec![
+            help::make_help_box("/", "Search"),
+            help::make_help_box("Z", "Exclude pattern"),
+            help::make_help_box("Shift+I", "Include only pattern"),
+            help::make_help_box("R", "Reset"),
+            help::make_help_box("Enter", "Finish"),
+        ]);
+        let (help_texts, help_boxes): (Vec<HelpText>, Vec<VisualBox>) = helps.into_iter().unzip();
         help::draw_help(help_texts, help_boxes, f, buffer_rect)
     }
 
@@ -78,10 +181,14 @@ impl<'path> FilePickerUi<'path> {
         &mut self,
         f: &mut tui::Frame<impl Backend>,
         size: Rect,
+        mode: InputMode,
         input_field: &mut InputField,
     ) -> Rect {
         let prompt_text = if size.width > 45 {
-            "Ignore pattern: "
+            match mode {
+                InputMode::IgnorePattern => "Ignore pattern: ",
+                InputMode::IncludePattern => "Include only pattern: ",
+            }
         } else {
             ":"
         };
@@ -130,10 +237,33 @@ where
                         Key::Down | Key::Char('j') => {
                             self.file_list.go_down();
                         }
+                        Key::PageUp => {
+                            self.file_list.go_page_up(self.file_widget.page_height());
+                        }
+                        Key::PageDown => {
+                            self.file_list.go_page_down(self.file_widget.page_height());
+                        }
+                        Key::Home => {
+                            self.file_list.go_first();
+                        }
+                        Key::End => {
+                            self.file_list.go_last();
+                        }
                         Key::Char('o') => {
                             self.file_list.toggle_folder();
                         }
-                        Key::Char('x') => {
+                        Key::Char('E') => {
+                            self.file_list.expand_all();
+                        }
+                        Key::Char('C') => {
+                            self.file_list.collapse_all();
+                        }
+                        Key::Char('p') => {
+                            if let Some(preview) = open_preview(&self.file_list) {
+                                self.mode = UiMode::Preview(preview);
+                            }
+                        }
+                        Key::Char('x') if self.file_list.len() > 0 => {
                             self.file_list.toggle_exclude_file();
                         }
                         Key::Char('r') => {
@@ -142,6 +272,12 @@ where
                         Key::Char('z') => {
                             self.mode = UiMode::Input(InputMode::IgnorePattern, InputField::new());
                         }
+                        Key::Char('I') => {
+                            self.mode = UiMode::Input(InputMode::IncludePattern, InputField::new());
+                        }
+                        Key::Char('/') => {
+                            self.mode = UiMode::Search(InputField::new());
+                        }
                         Key::Char('\n') | Key::Char('\r') => {
                             return Some(UiStateReaction::Exit);
                         }
@@ -165,6 +301,12 @@ where
                                     Err(err) => UiMode::Error(err.to_string()),
                                 }
                             }
+                            InputMode::IncludePattern => {
+                                self.mode = match self.file_list.include_pattern(&pattern) {
+                                    Ok(()) => UiMode::List,
+                                    Err(err) => UiMode::Error(err.to_string()),
+                                }
+                            }
                         }
                     }
                     Key::Char('\t') => {}
@@ -173,6 +315,10 @@ where
                     Key::Delete => input_field.delete_char(),
                     Key::Left => input_field.caret_move_left(),
                     Key::Right => input_field.caret_move_right(),
+                    Key::Alt('b') => input_field.caret_move_word_left(),
+                    Key::Alt('f') => input_field.caret_move_word_right(),
+                    Key::Ctrl('u') => input_field.clear_to_start(),
+                    Key::Ctrl('k') => input_field.clear_to_end(),
                     _ => {}
                 };
                 None
@@ -181,19 +327,92 @@ where
                 self.mode = UiMode::List;
                 None
             }
+            UiMode::Preview(preview) => {
+                if !preview_input(preview, key) {
+                    self.mode = UiMode::List;
+                }
+                None
+            }
+            UiMode::Search(input_field) => {
+                match key {
+                    Key::Esc | Key::Ctrl('c') => {
+                        self.file_list.cancel_filter();
+                        self.mode = UiMode::List;
+                        return None;
+                    }
+                    Key::Char('\n') | Key::Char('\r') => {
+                        self.file_list.accept_filter();
+                        self.mode = UiMode::List;
+                        return None;
+                    }
+                    Key::Up => {
+                        self.file_list.go_up();
+                        return None;
+                    }
+                    Key::Down => {
+                        self.file_list.go_down();
+                        return None;
+                    }
+                    Key::Left => input_field.caret_move_left(),
+                    Key::Right => input_field.caret_move_right(),
+                    Key::Alt('b') => input_field.caret_move_word_left(),
+                    Key::Alt('f') => input_field.caret_move_word_right(),
+                    Key::Ctrl('u') => input_field.clear_to_start(),
+                    Key::Ctrl('k') => input_field.clear_to_end(),
+                    Key::Backspace => input_field.backspace_char(),
+                    Key::Delete => input_field.delete_char(),
+                    Key::Char('\t') => return None,
+                    Key::Char(c) => input_field.add_char(c),
+                    _ => return None,
+                }
+                self.file_list.filtered_view(&input_field.consume_input());
+                None
+            }
         }
     }
 
+    fn on_mouse(&mut self, event: MouseEvent) -> Option<crate::ui::UiStateReaction> {
+        match &mut self.mode {
+            UiMode::List => on_list_mouse(&mut self.file_list, &self.file_widget, event),
+            UiMode::Preview(preview) => on_preview_mouse(preview, event),
+            _ => {}
+        }
+        None
+    }
+
+    fn on_paste(&mut self, text: String) -> Option<crate::ui::UiStateReaction> {
+        match &mut self.mode {
+            UiMode::Input(_, input_field) => input_field.paste(&text),
+            UiMode::Search(input_field) => {
+                input_field.paste(&text);
+                self.file_list.filtered_view(&input_field.consume_input());
+            }
+            _ => {}
+        }
+        None
+    }
+
     fn on_tick(&mut self) -> Option<crate::ui::UiStateReaction> {
         None
     }
 
     fn draw(&mut self, f: &mut tui::Frame<B>) {
+        if let UiMode::Preview(preview) = &mut self.mode {
+            preview.draw(f, f.size());
+            return;
+        }
         let mut mode = self.mode.clone();
         let remaining = match &mut mode {
             UiMode::List => self.draw_help(f, f.size()),
-            UiMode::Input(_, input_field) => self.draw_prompt(f, f.size(), input_field),
+            UiMode::Input(input_mode, input_field) => {
+                self.draw_prompt(f, f.size(), *input_mode, input_field)
+            }
             UiMode::Error(err_msg) => self.draw_error(f, err_msg),
+            UiMode::Search(input_field) => {
+                let prompt_text = if f.size().width > 45 { "Search: " } else { "/" };
+                input::draw_input(f, f.size(), input_field, prompt_text)
+            }
+            UiMode::Preview(_) => unreachable!("Previews are drawn above."),
         };
         let list_block = Block::default().borders(tui::widgets::Borders::ALL);
         let block_inner = list_block.inner(remaining);
@@ -205,13 +424,22 @@ where
 pub struct FileTreeUi<'path> {
     file_list: FileList<'path>,
     file_widget: FileListWidget,
+    /// Either `List` or `Preview`, as there is no input to take.
+    mode: UiMode,
 }
 
 impl<'path> FileTreeUi<'path> {
-    pub fn new(base_dir: &'path Path) -> Self {
+    /// If `only_text` is set, files that look binary are not shown. If `max_depth`
+    /// is given, no more than that many levels of the tree are shown.
+    pub fn new(base_dir: &'path Path, only_text: bool, max_depth: Option<usize>) -> Self {
+        let mut file_list = FileList::new_filtered(base_dir, only_text);
+        if let Some(max_depth) = max_depth {
+            file_list.limit_depth(max_depth);
+        }
         FileTreeUi {
-            file_list: FileList::new(base_dir),
+            file_list,
             file_widget: FileListWidget::default(),
+            mode: UiMode::List,
         }
     }
 }
@@ -222,6 +450,12 @@ impl<'path, B: Backend> UiState<B> for FileTreeUi<'path> {
     }
 
     fn on_key(&mut self, key: Key) -> Option<crate::ui::UiStateReaction> {
+        if let UiMode::Preview(preview) = &mut self.mode {
+            if !preview_input(preview, key) {
+                self.mode = UiMode::List;
+            }
+            return None;
+        }
         match key {
             Key::Char('k') | Key::Up => {
                 self.file_list.go_up();
@@ -231,28 +465,86 @@ impl<'path, B: Backend> UiState<B> for FileTreeUi<'path> {
                 self.file_list.go_down();
                 None
             }
+            Key::PageUp => {
+                self.file_list.go_page_up(self.file_widget.page_height());
+                None
+            }
+            Key::PageDown => {
+                self.file_list.go_page_down(self.file_widget.page_height());
+                None
+            }
+            Key::Home => {
+                self.file_list.go_first();
+                None
+            }
+            Key::End => {
+                self.file_list.go_last();
+                None
+            }
             Key::Char('o') => {
                 self.file_list.toggle_folder();
                 None
             }
-            Key::Char('\n') | Key::Char('\r') | Key::Ctrl('c') | Key::Char('q') => Some(UiStateReaction::Exit),
+            Key::Char('E') => {
+                self.file_list.expand_all();
+                None
+            }
+            Key::Char('C') => {
+                self.file_list.collapse_all();
+                None
+            }
+            Key::Char('p') => {
+                if let Some(preview) = open_preview(&self.file_list) {
+                    self.mode = UiMode::Preview(preview);
+                }
+                None
+            }
+            Key::Char('\n') | Key::Char('\r') | Key::Ctrl('c') | Key::Char('q') => {
+                Some(UiStateReaction::Exit)
+            }
             _ => None,
         }
     }
 
+    fn on_mouse(&mut self, event: MouseEvent) -> Option<crate::ui::UiStateReaction> {
+        match &mut self.mode {
+            UiMode::Preview(preview) => on_preview_mouse(preview, event),
+            _ => on_list_mouse(&mut self.file_list, &self.file_widget, event),
+        }
+        None
+    }
+
     fn on_tick(&mut self) -> Option<crate::ui::UiStateReaction> {
         None
     }
 
     fn draw(&mut self, f: &mut tui::Frame<B>) {
-        let (help_texts, help_boxes): (Vec<String>, Vec<VisualBox>) = vec![
-            super::help::make_help_box("Up/K", "Move up in list"),
-            super::help::make_help_box("Down/J", "Move down in list"),
-            super::help::make_help_box("O", "Open/Close folder"),
-            super::help::make_help_box("Enter/Q", "Exit"),
-        ]
-        .into_iter()
-        .unzip();
+        if let UiMode::Preview(preview) = &mut self.mode {
+            preview.draw(f, f.size());
+            return;
+        }
+        let mut helps = vec![];
+        if self.file_list.len() > 0 {
+            helps.extend(vec![
+                help::make_help_box("Up/K", "Move up in list"),
+                help::make_help_box("Down/J", "Move down in list"),
+                help::make_help_box("PgUp/PgDn", "Move a page"),
+                help::make_help_box("Home/End", "First/Last entry"),
+                help::make_help_box_enabled_if(
+                    self.file_list.is_highlight_dir(),
+                    "O",
+                    "Open/Close folder",
+                ),
+                help::make_help_box("Shift+E/C", "Expand/Collapse all"),
+                help::make_help_box_enabled_if(
+                    !self.file_list.is_highlight_dir(),
+                    "P",
+                    "Preview file",
+                ),
+            ]);
+        }
+        helps.push(help::make_help_box("Enter/Q", "Exit"));
+        let (help_texts, help_boxes): (Vec<HelpText>, Vec<VisualBox>) = helps.into_iter().unzip();
         let remaining = crate::ui::help::draw_help(help_texts, help_boxes, f, f.size());
         let list_block = Block::default().borders(tui::widgets::Borders::ALL);
         let block_inner = list_block.inner(remaining);
@@ -278,6 +570,7 @@ fn draw_list(
         file_widget.buffer_start = file_list.highlight.saturating_sub(size.height as usize) + 1;
     }
 
+    file_widget.area = size;
     let list_size = file_list.len();
     let buffer_start = file_widget.buffer_start;
     let buffer_end = min(file_widget.buffer_start + size.height as usize, list_size);
@@ -315,7 +608,13 @@ fn draw_list(
         if list_elem.path.is_dir() {
             file_name_style = file_name_style.add_modifier(Modifier::BOLD | Modifier::ITALIC);
         }
-        let indented_file_name = format!("{}{}", " ".repeat(list_elem.depth), file_name);
+        let limit_marker = if list_elem.depth_limited { " …" } else { "" };
+        let indented_file_name = format!(
+            "{}{}{}",
+            " ".repeat(list_elem.depth),
+            file_name,
+            limit_marker
+        );
         let file_name_paragraph = Paragraph::new(indented_file_name).style(file_name_style);
         let render_to = Rect::new(size.left(), render_y, line_width, 1);
         f.render_widget(file_name_paragraph, render_to);
@@ -0,0 +1,247 @@
+use crate::ui::{
+    help::{self, HelpText},
+    layout::VisualBox,
+};
+use std::{fs::File, io::Read, path::Path};
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// How many bytes of a file are shown, at most. Only this much is read, however
+/// long the file (or any of its lines).
+const PREVIEW_LENGTH: u64 = 64 * 1024;
+
+/// A scrollable view over the contents of a file.
+#[derive(Clone)]
+pub struct FilePreview {
+    title: String,
+    text: String,
+    scroll: u16,
+    /// How many lines fit in the preview, as of the last `draw`.
+    last_height: u16,
+    /// How wide the preview is, as of the last `draw`.
+    last_width: u16,
+    /// How many lines `text` takes up once wrapped to `last_width`.
+    wrapped_lines: usize,
+}
+
+/// How many lines `text` takes up once wrapped to `width` columns, the way a
+/// `Paragraph` with `Wrap { trim: false }` wraps it: on word boundaries, or
+/// anywhere in words wider than a line.
+fn wrapped_line_count(text: &str, width: u16) -> usize {
+    if width == 0 {
+        return 0;
+    }
+    let is_whitespace =
+        |symbol: &str| symbol.chars().all(char::is_whitespace) && symbol != "\u{a0}";
+    let mut symbols = text
+        .lines()
+        .flat_map(|line| line.graphemes(true).chain(std::iter::once("\n")));
+    let mut count = 0;
+    let mut current_line = vec![];
+    let mut next_line = vec![];
+    loop {
+        std::mem::swap(&mut current_line, &mut next_line);
+        next_line.clear();
+        let mut line_width = current_line.iter().map(|s: &&str| s.width()).sum::<usize>();
+        let mut symbols_to_word_end = 0;
+        let mut previous_whitespace = false;
+        let mut exhausted = true;
+        for symbol in &mut symbols {
+            exhausted = false;
+            let whitespace = is_whitespace(symbol);
+            if symbol.width() > width as usize {
+                continue;
+            }
+            if symbol == "\n" {
+                break;
+            }
+            if whitespace && !previous_whitespace {
+                symbols_to_word_end = current_line.len();
+            }
+            current_line.push(symbol);
+            line_width += symbol.width();
+            if line_width > width as usize {
+                // Without a word break, the line is broken at its end.
+                let break_at = if symbols_to_word_end != 0 {
+                    symbols_to_word_end
+                } else {
+                    current_line.len() - 1
+                };
+                let remainder = &current_line[break_at..];
+                if let Some(start) = remainder.iter().position(|s| !is_whitespace(s)) {
+                    next_line.extend_from_slice(&remainder[start..]);
+                }
+                current_line.truncate(break_at);
+                break;
+            }
+            previous_whitespace = whitespace;
+        }
+        if exhausted && current_line.is_empty() {
+            return count;
+        }
+        count += 1;
+    }
+}
+
+impl FilePreview {
+    /// Reads the start of the file at `path`. Binary files (and files that cannot
+    /// be read) are described, rather than shown.
+    ///
+    /// A file longer than [`PREVIEW_LENGTH`] is cut where the limit falls, even in
+    /// the middle of a line, and marked with an ellipsis and "(truncated)".
+    pub fn load(path: &Path, title: String) -> Self {
+        let text = if crate::binary::is_probably_binary(path) {
+            let size = path.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            format!("(binary file, {} bytes)", size)
+        } else {
+            let mut buffer = vec![];
+            let read = File::open(path)
+                .and_then(|file| file.take(PREVIEW_LENGTH + 1).read_to_end(&mut buffer));
+            match read {
+                Ok(_) => {
+                    let truncated = buffer.len() as u64 > PREVIEW_LENGTH;
+                    if truncated {
+                        buffer.truncate(PREVIEW_LENGTH as usize);
+                        // The cut may fall within a character, which is dropped
+                        // rather than shown as a replacement character.
+                        if let Err(err) = std::str::from_utf8(&buffer) {
+                            if err.error_len().is_none() {
+                                buffer.truncate(err.valid_up_to());
+                            }
+                        }
+                    }
+                    let mut text = String::from_utf8_lossy(&buffer).replace('\t', "    ");
+                    if truncated {
+                        text.push_str("… (truncated)");
+                    }
+                    text
+                }
+                Err(err) => format!("(could not read file: {})", err),
+            }
+        };
+        FilePreview {
+            title,
+            text,
+            scroll: 0,
+            last_height: 0,
+            last_width: 0,
+            wrapped_lines: 0,
+        }
+    }
+
+    /// Wraps the text to a new width, keeping the scroll within it.
+    fn set_width(&mut self, width: u16) {
+        if width != self.last_width {
+            self.last_width = width;
+            self.wrapped_lines = wrapped_line_count(&self.text, width);
+            self.scroll = std::cmp::min(self.scroll, self.max_scroll());
+        }
+    }
+
+    fn max_scroll(&self) -> u16 {
+        let lines = self.wrapped_lines.saturating_sub(1);
+        std::cmp::min(lines, u16::MAX as usize) as u16
+    }
+
+    pub fn scroll_up(&mut self, lines: u16) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    pub fn scroll_down(&mut self, lines: u16) {
+        self.scroll = std::cmp::min(self.scroll.saturating_add(lines), self.max_scroll());
+    }
+
+    pub fn scroll_page_up(&mut self) {
+        self.scroll_up(std::cmp::max(self.last_height, 1));
+    }
+
+    pub fn scroll_page_down(&mut self) {
+        self.scroll_down(std::cmp::max(self.last_height, 1));
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll = 0;
+    }
+
+    pub fn draw(&mut self, f: &mut tui::Frame<impl Backend>, size: Rect) {
+        let helps = vec![
+            help::make_help_box("Up/K", "Scroll up"),
+            help::make_help_box("Down/J", "Scroll down"),
+            help::make_help_box("PgUp/PgDn", "Scroll a page"),
+            help::make_help_box("Esc", "Back to list"),
+        ];
+        let (help_texts, help_boxes): (Vec<HelpText>, Vec<VisualBox>) = helps.into_iter().unzip();
+        let remaining = help::draw_help(help_texts, help_boxes, f, size);
+
+        let block = Block::default().borders(Borders::ALL);
+        let inner = block.inner(remaining);
+        self.last_height = inner.height;
+        self.set_width(inner.width);
+        let paragraph = Paragraph::new(self.text.as_str())
+            .block(block.title(self.title.as_str()))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        f.render_widget(paragraph, remaining);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_lines_can_be_scrolled_through() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("minified.js");
+        std::fs::write(&path, "a".repeat(10 * 1024)).unwrap();
+        let mut preview = FilePreview::load(&path, "minified.js".to_string());
+
+        preview.set_width(80);
+        preview.scroll_down(u16::MAX);
+        assert_eq!(preview.scroll, 127);
+
+        // Narrowing the preview wraps the line into more lines, and widening it
+        // back keeps the scroll within the fewer lines.
+        preview.set_width(40);
+        preview.scroll_down(u16::MAX);
+        assert_eq!(preview.scroll, 255);
+        preview.set_width(80);
+        assert_eq!(preview.scroll, 127);
+    }
+
+    #[test]
+    fn long_files_are_cut_mid_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("minified.js");
+        // A multibyte character straddles the limit.
+        let mut line = "a".repeat(PREVIEW_LENGTH as usize - 1);
+        line.push_str(&"é".repeat(1024 * 1024));
+        std::fs::write(&path, &line).unwrap();
+
+        let mut preview = FilePreview::load(&path, "minified.js".to_string());
+        assert_eq!(preview.text.lines().count(), 1);
+        assert!(preview
+            .text
+            .starts_with(&"a".repeat(PREVIEW_LENGTH as usize - 1)));
+        assert!(preview.text.ends_with("a… (truncated)"));
+
+        preview.set_width(100);
+        preview.scroll_down(u16::MAX);
+        assert_eq!(preview.scroll, 655);
+    }
+
+    #[test]
+    fn lines_are_wrapped_on_word_boundaries() {
+        assert_eq!(wrapped_line_count("", 10), 0);
+        assert_eq!(wrapped_line_count("one\n\nthree", 10), 3);
+        assert_eq!(wrapped_line_count("0123456789", 10), 1);
+        assert_eq!(wrapped_line_count("0123456789a", 10), 2);
+        assert_eq!(wrapped_line_count("four five six", 10), 2);
+        assert_eq!(wrapped_line_count("日本語の文", 4), 3);
+    }
+}
@@ -1,15 +1,45 @@
-use tui::{backend::Backend, layout::Rect, style::Style, widgets::{Block, Paragraph}};
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    style::{Modifier, Style},
+    widgets::{Block, Paragraph},
+};
 
 use crate::ui::layout::VisualBox;
 
-pub fn make_help_box(button: &'static str, help: &'static str) -> (String, VisualBox) {
-    let help_text = format!("[{}] {}", button, help);
-    let help_box = VisualBox::new(help_text.len() as u16, 1);
-    (help_text, help_box)
+/// The text of a help box, and whether the action it describes is currently available.
+pub struct HelpText {
+    text: String,
+    enabled: bool,
 }
 
-pub fn draw_help(help_texts: Vec<String>, help_boxes: Vec<VisualBox>, f: &mut tui::Frame<impl Backend>, buffer_rect: Rect) -> Rect {
+pub fn make_help_box(button: &'static str, help: &'static str) -> (HelpText, VisualBox) {
+    make_help_box_enabled_if(true, button, help)
+}
+
+/// Like [`make_help_box`], but the box is drawn dimmed if `enabled` is false, to
+/// signal that the action does not apply in the current context.
+pub fn make_help_box_enabled_if(
+    enabled: bool,
+    button: &'static str,
+    help: &'static str,
+) -> (HelpText, VisualBox) {
+    let text = format!("[{}] {}", button, help);
+    let help_box = VisualBox::new(text.len() as u16, 1);
+    (HelpText { text, enabled }, help_box)
+}
+
+pub fn draw_help(
+    help_texts: Vec<HelpText>,
+    help_boxes: Vec<VisualBox>,
+    f: &mut tui::Frame<impl Backend>,
+    buffer_rect: Rect,
+) -> Rect {
     let positions = crate::ui::layout::distribute(buffer_rect.width, &help_boxes);
+    if positions.is_empty() || buffer_rect.height == 0 {
+        // Nothing to draw, or no room to draw it in.
+        return buffer_rect;
+    }
     let new_height = std::cmp::min(
         positions.last().unwrap().1 - positions[0].1 + 1,
         buffer_rect.height,
@@ -36,11 +66,23 @@ pub fn draw_help(help_texts: Vec<String>, help_boxes: Vec<VisualBox>, f: &mut tu
         if y > buffer_rect.bottom() {
             break;
         }
+        if x >= buffer_rect.right() {
+            continue;
+        }
 
-        let width = text.len() as u16;
+        // Boxes wider than the buffer are cut off, rather than drawn outside of it.
+        let width = std::cmp::min(text.text.len() as u16, buffer_rect.right() - x);
         let height = std::cmp::min(1, buffer_rect.height);
         let y = std::cmp::min(y, buffer_rect.bottom().saturating_sub(1));
-        f.render_widget(Paragraph::new(text), Rect::new(x, y, width, height));
+        let style = if text.enabled {
+            Style::default()
+        } else {
+            Style::default().add_modifier(Modifier::DIM)
+        };
+        f.render_widget(
+            Paragraph::new(text.text).style(style),
+            Rect::new(x, y, width, height),
+        );
     }
 
     Rect::new(
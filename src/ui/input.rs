@@ -7,15 +7,21 @@ use tui::{
     text::{Span, Spans},
     widgets::Paragraph,
 };
+use unicode_segmentation::UnicodeSegmentation;
 
 /// A single-line input field, with a caret.
 ///
+/// The caret moves over (extended) grapheme clusters rather than `char`s, so that
+/// combining sequences and emoji are treated as a single, indivisible character.
+///
 /// This struct does not handle translating user input to actions on the input
 /// field, but rather provides functions to act on the input.
 #[derive(Clone)]
 pub struct InputField {
     input_buffer: String,
+    /// Position of the caret, in grapheme clusters.
     caret_position: usize,
+    /// First grapheme cluster shown when rendering.
     buffer_start: usize,
 }
 
@@ -34,24 +40,55 @@ impl InputField {
         }
     }
 
+    /// Number of grapheme clusters in the buffer (including the trailing space
+    /// where the caret rests at the end of the input).
+    fn grapheme_count(&self) -> usize {
+        self.input_buffer.graphemes(true).count()
+    }
+
+    /// Byte offset into `input_buffer` of the grapheme cluster at `index`.
+    fn byte_offset(&self, index: usize) -> usize {
+        self.input_buffer
+            .grapheme_indices(true)
+            .nth(index)
+            .map_or(self.input_buffer.len(), |(offset, _)| offset)
+    }
+
     pub fn add_char(&mut self, c: char) {
-        self.input_buffer.insert(self.caret_position, c);
-        self.caret_position += 1;
+        let offset = self.byte_offset(self.caret_position);
+        self.input_buffer.insert(offset, c);
+        // The new character may have merged into the preceding grapheme cluster
+        // (e.g. a combining accent), so recount up to the end of the insertion.
+        self.caret_position = self.input_buffer[..offset + c.len_utf8()]
+            .graphemes(true)
+            .count();
+    }
+
+    /// Inserts `text` at the caret. As this is a single-line field, line breaks
+    /// (and other control characters) are left out.
+    pub fn paste(&mut self, text: &str) {
+        for c in text.chars().filter(|c| !c.is_control()) {
+            self.add_char(c);
+        }
     }
 
     pub fn backspace_char(&mut self) {
         if self.caret_position == 0 {
             return;
         }
-        self.input_buffer.remove(self.caret_position - 1);
+        let start = self.byte_offset(self.caret_position - 1);
+        let end = self.byte_offset(self.caret_position);
+        self.input_buffer.replace_range(start..end, "");
         self.caret_position = self.caret_position.saturating_sub(1);
     }
 
     pub fn delete_char(&mut self) {
-        if self.caret_position == self.input_buffer.len() - 1 {
+        if self.caret_position == self.grapheme_count() - 1 {
             return;
         }
-        self.input_buffer.remove(self.caret_position);
+        let start = self.byte_offset(self.caret_position);
+        let end = self.byte_offset(self.caret_position + 1);
+        self.input_buffer.replace_range(start..end, "");
     }
 
     pub fn caret_move_left(&mut self) {
@@ -60,14 +97,58 @@ impl InputField {
 
     pub fn caret_move_right(&mut self) {
         self.caret_position = min(
-            self.input_buffer.len().saturating_sub(1),
+            self.grapheme_count().saturating_sub(1),
             self.caret_position + 1,
         );
     }
 
+    /// Moves the caret to the start of the word before it (or the word it is in),
+    /// where words are runs of alphanumeric characters.
+    pub fn caret_move_word_left(&mut self) {
+        let graphemes = self.input_buffer.graphemes(true).collect::<Vec<&str>>();
+        let mut position = self.caret_position;
+        while position > 0 && !is_word_grapheme(graphemes[position - 1]) {
+            position -= 1;
+        }
+        while position > 0 && is_word_grapheme(graphemes[position - 1]) {
+            position -= 1;
+        }
+        self.caret_position = position;
+    }
+
+    /// Moves the caret to the end of the word after it (or the word it is in),
+    /// where words are runs of alphanumeric characters.
+    pub fn caret_move_word_right(&mut self) {
+        let graphemes = self.input_buffer.graphemes(true).collect::<Vec<&str>>();
+        // The trailing space is not part of the input.
+        let end = graphemes.len() - 1;
+        let mut position = self.caret_position;
+        while position < end && !is_word_grapheme(graphemes[position]) {
+            position += 1;
+        }
+        while position < end && is_word_grapheme(graphemes[position]) {
+            position += 1;
+        }
+        self.caret_position = position;
+    }
+
+    /// Removes everything before the caret.
+    pub fn clear_to_start(&mut self) {
+        let offset = self.byte_offset(self.caret_position);
+        self.input_buffer.replace_range(..offset, "");
+        self.caret_position = 0;
+    }
+
+    /// Removes everything from the caret onwards.
+    pub fn clear_to_end(&mut self) {
+        let offset = self.byte_offset(self.caret_position);
+        let end = self.input_buffer.len() - 1;
+        self.input_buffer.replace_range(offset..end, "");
+    }
+
     /// Return the string that should be rendered when displaying this input field
-    /// (in a `width`-wide viewport), and the character that should be highlighted/
-    /// have a caret before it.
+    /// (in a `width`-wide viewport), and the grapheme cluster that should be
+    /// highlighted/have a caret before it.
     pub fn render(&mut self, width: u16) -> (String, usize) {
         if self.caret_position < self.buffer_start + 1 {
             self.buffer_start = self.caret_position;
@@ -75,10 +156,13 @@ impl InputField {
             self.buffer_start = self.caret_position.saturating_sub(width as usize) + 1;
         }
         let buffer_start = self.buffer_start;
-        let buffer_end = min(buffer_start + width as usize, self.input_buffer.len());
         let highlighted = self.caret_position - buffer_start;
         (
-            self.input_buffer[buffer_start..buffer_end].to_string(),
+            self.input_buffer
+                .graphemes(true)
+                .skip(buffer_start)
+                .take(width as usize)
+                .collect(),
             highlighted,
         )
     }
@@ -89,6 +173,10 @@ impl InputField {
     }
 }
 
+fn is_word_grapheme(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(char::is_alphanumeric)
+}
+
 pub fn draw_input(
     f: &mut tui::Frame<impl Backend>,
     size: Rect,
@@ -99,16 +187,17 @@ pub fn draw_input(
     let remaining = Rect::new(size.left(), size.top(), size.width, size.height - 1);
 
     let (shown_input, highlighted) = input_field.render(remaining.width - prompt_text.len() as u16);
+    let shown_graphemes = shown_input.graphemes(true).collect::<Vec<&str>>();
 
     f.render_widget(
         Paragraph::new(vec![Spans::from(vec![
             Span::styled(prompt_text, Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(&shown_input[0..highlighted]),
+            Span::raw(shown_graphemes[..highlighted].concat()),
             Span::styled(
-                shown_input.chars().nth(highlighted).unwrap().to_string(),
+                shown_graphemes[highlighted],
                 Style::default().bg(Color::White).fg(Color::Black),
             ),
-            Span::raw(&shown_input[highlighted + 1..]),
+            Span::raw(shown_graphemes[highlighted + 1..].concat()),
         ])])
         .style(Style::default().bg(Color::Green).fg(Color::Black)),
         prompt_rect,
@@ -116,3 +205,61 @@ pub fn draw_input(
 
     remaining
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_caret_moves_over_combining_accents_whole() {
+        // An `e` followed by a combining acute accent is a single character.
+        let mut field = InputField::new_with_content("ae\u{301}b".to_string());
+        field.caret_move_right();
+        field.caret_move_right();
+        assert_eq!(field.render(10), ("ae\u{301}b ".to_string(), 2));
+        field.caret_move_left();
+        assert_eq!(field.render(10).1, 1);
+    }
+
+    #[test]
+    fn the_caret_moves_over_emoji_whole() {
+        // A family emoji is several code points joined by zero-width joiners.
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let mut field = InputField::new_with_content(format!("{}!", family));
+        field.caret_move_right();
+        assert_eq!(field.render(10), (format!("{}! ", family), 1));
+        field.backspace_char();
+        assert_eq!(field.consume_input(), "!");
+    }
+
+    #[test]
+    fn combining_accents_typed_after_a_letter_join_it() {
+        let mut field = InputField::new();
+        field.add_char('e');
+        field.add_char('\u{301}');
+        field.add_char('x');
+        assert_eq!(field.render(10).1, 2);
+        field.caret_move_left();
+        field.backspace_char();
+        assert_eq!(field.consume_input(), "x");
+    }
+
+    #[test]
+    fn multibyte_characters_are_inserted_and_deleted_whole() {
+        let mut field = InputField::new();
+        field.paste("a你b");
+        field.caret_move_left();
+        field.add_char('é');
+        assert_eq!(field.consume_input(), "a你éb");
+        assert_eq!(field.render(10).1, 3);
+
+        field.backspace_char();
+        assert_eq!(field.consume_input(), "a你b");
+        field.caret_move_left();
+        field.delete_char();
+        assert_eq!(field.consume_input(), "ab");
+        field.caret_move_word_right();
+        field.clear_to_start();
+        assert_eq!(field.consume_input(), "");
+    }
+}
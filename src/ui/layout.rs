@@ -13,13 +13,16 @@ impl VisualBox {
 
 fn get_splits(max_width: u16, elements: &[VisualBox]) -> Vec<usize> {
     type Badness = u64;
+    if elements.is_empty() {
+        return vec![];
+    }
     let mut break_memo: Vec<Option<usize>> = vec![None; elements.len()];
     let mut badness_memo: Vec<Option<Badness>> = vec![None; elements.len()];
 
     let compute_badness = |i: usize, j: usize| -> Badness {
         let total_width = (i..j).map(|k| elements[k].width).sum::<u16>();
         if total_width > max_width {
-            std::u64::MAX
+            u64::MAX
         } else {
             ((max_width - total_width) as u64).pow(3)
         }
@@ -30,8 +33,7 @@ fn get_splits(max_width: u16, elements: &[VisualBox]) -> Vec<usize> {
     let mut best_badness_stack = vec![Badness::MAX];
     let mut best_break_stack = vec![1_usize];
 
-    while !start_stack.is_empty() {
-        let start = start_stack.pop().unwrap();
+    while let Some(start) = start_stack.pop() {
         let length = length_stack.pop().unwrap();
         let newline_before = start + length;
 
@@ -48,11 +50,12 @@ fn get_splits(max_width: u16, elements: &[VisualBox]) -> Vec<usize> {
                         start_stack.push(start);
                         length_stack.push(length);
 
-                        // Prepare the recursion frame
+                        // Prepare the recursion frame. The best break defaults to
+                        // a line of one element, in case every line is too wide.
                         start_stack.push(newline_before);
                         length_stack.push(1);
                         best_badness_stack.push(Badness::MAX);
-                        best_break_stack.push(1);
+                        best_break_stack.push(newline_before + 1);
                         continue;
                     }
                 }
@@ -113,8 +116,12 @@ fn get_splits(max_width: u16, elements: &[VisualBox]) -> Vec<usize> {
 /// # Returns
 ///
 /// A vector of relative positions (starting at `(0, 0)`) denoting where each element
-/// should be placed to minimize badness, respectively to each index.
+/// should be placed to minimize badness, respectively to each index. If there is
+/// no room (`max_width` is zero), no positions are returned.
 pub fn distribute(max_width: u16, elements: &[VisualBox]) -> Vec<Position> {
+    if max_width == 0 {
+        return vec![];
+    }
     let splits = get_splits(max_width, elements);
 
     let mut positions = Vec::<Position>::new();
@@ -127,7 +134,7 @@ pub fn distribute(max_width: u16, elements: &[VisualBox]) -> Vec<Position> {
 
         let line_height = line_elements.iter().map(|x| x.height).max().unwrap();
         let content_width: u16 = line_elements.iter().map(|x| x.width).sum();
-        let whitespace = (max_width - content_width) / (split_end - split_start) as u16;
+        let whitespace = max_width.saturating_sub(content_width) / (split_end - split_start) as u16;
 
         let mut filled = 0;
         for visual_box in line_elements {
@@ -141,9 +148,31 @@ pub fn distribute(max_width: u16, elements: &[VisualBox]) -> Vec<Position> {
     positions
 }
 
+/// Splits `word` into pieces of at most `max_width` characters, so that words
+/// wider than a line (e.g., long paths) can still be laid out. Pieces that are
+/// only whitespace (the word's trailing space, cut off) are dropped.
+fn hard_break(word: &str, max_width: u16) -> Vec<&str> {
+    let max_width = std::cmp::max(max_width, 1) as usize;
+    if word.chars().count() <= max_width {
+        return vec![word];
+    }
+    let boundaries = word
+        .char_indices()
+        .map(|(i, _)| i)
+        .step_by(max_width)
+        .chain(std::iter::once(word.len()))
+        .collect::<Vec<usize>>();
+    boundaries
+        .windows(2)
+        .map(|bounds| &word[bounds[0]..bounds[1]])
+        .filter(|piece| !piece.trim().is_empty())
+        .collect()
+}
+
 /// Applies the actual TeX algorithm to the given text, by splitting the text
 /// into words (by splitting at spaces), creating `VisualBox`es for each word,
-/// and applying the `distribute` algorithm.
+/// and applying the `distribute` algorithm. Words wider than `max_width` are
+/// broken into pieces first.
 ///
 /// # Returns
 ///
@@ -154,9 +183,14 @@ pub fn distribute_text(text: &str, max_width: u16) -> (String, usize) {
     for paragraph in text.split('\n') {
         let (words, boxes) = paragraph
             .split_inclusive(char::is_whitespace)
+            .flat_map(|word| hard_break(word, max_width))
             .map(|word| (word, VisualBox::new(word.chars().count() as u16, 1)))
             .unzip::<&str, VisualBox, Vec<&str>, Vec<VisualBox>>();
         let splits = get_splits(max_width, &boxes);
+        if splits.is_empty() {
+            // An empty paragraph is still a (blank) line.
+            lines.push("\n".to_string());
+        }
         for i in 0..splits.len() {
             let split_start = if i == 0 { 0 } else { splits[i - 1] };
             let split_end = splits[i];
@@ -169,3 +203,33 @@ pub fn distribute_text(text: &str, max_width: u16) -> (String, usize) {
     }
     (lines.concat(), lines.len())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_words_are_broken_into_line_wide_pieces() {
+        let word = "x".repeat(200);
+        let pieces = hard_break(&word, 20);
+        assert_eq!(pieces.len(), 10);
+        assert!(pieces.iter().all(|piece| piece.chars().count() == 20));
+        assert_eq!(pieces.concat(), word);
+    }
+
+    #[test]
+    fn long_words_are_broken_at_character_boundaries() {
+        let word = "é".repeat(30);
+        let pieces = hard_break(&word, 20);
+        assert_eq!(pieces, vec!["é".repeat(20), "é".repeat(10)]);
+    }
+
+    #[test]
+    fn long_words_are_wrapped_within_the_width() {
+        let word = "x".repeat(200);
+        let (text, lines) = distribute_text(&format!("a {} b", word), 20);
+        assert!(text.lines().all(|line| line.chars().count() <= 20));
+        assert_eq!(text.lines().count(), lines);
+        assert_eq!(text.replace(char::is_whitespace, ""), format!("a{}b", word));
+    }
+}
@@ -25,6 +25,8 @@ where
     phantom: PhantomData<&'t T>,
     pub highlight: usize,
     buffer_start: usize,
+    /// How many entries fit in the list, as of the last `draw`.
+    last_height: usize,
     elements: Vec<T>,
 }
 
@@ -37,6 +39,7 @@ where
             phantom: PhantomData,
             highlight: 0,
             buffer_start: 0,
+            last_height: 0,
             elements,
         }
     }
@@ -61,6 +64,31 @@ where
         };
     }
 
+    /// How many entries were visible when the list was last drawn, to move
+    /// through the list a page at a time.
+    pub fn last_height(&self) -> usize {
+        self.last_height
+    }
+
+    pub fn go_page_up(&mut self, height: usize) {
+        self.highlight = self.highlight.saturating_sub(std::cmp::max(height, 1));
+    }
+
+    pub fn go_page_down(&mut self, height: usize) {
+        self.highlight = std::cmp::min(
+            self.highlight.saturating_add(std::cmp::max(height, 1)),
+            self.elements.len().saturating_sub(1),
+        );
+    }
+
+    pub fn go_first(&mut self) {
+        self.highlight = 0;
+    }
+
+    pub fn go_last(&mut self) {
+        self.highlight = self.elements.len().saturating_sub(1);
+    }
+
     pub fn remove_entry(&mut self, entry: usize) {
         self.elements.remove(entry);
         if entry <= self.highlight {
@@ -72,11 +100,16 @@ where
         self.elements[at] = value;
     }
 
+    pub fn swap_entries(&mut self, a: usize, b: usize) {
+        self.elements.swap(a, b);
+    }
+
     pub fn draw(&mut self, f: &mut tui::Frame<impl Backend>, size: Rect) {
+        self.last_height = size.height as usize;
         if self.len() == 0 {
             return;
         }
-        
+
         if self.highlight < self.buffer_start {
             self.buffer_start = self.highlight;
         } else if self.highlight > (self.buffer_start + size.height as usize).saturating_sub(1) {
@@ -1,7 +1,14 @@
-use std::{sync::Arc, time::Duration};
+use colored::Colorize;
+use parking_lot::Mutex;
+use std::{
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    time::Duration,
+};
 use termion::{
-    event::Key,
-    input::TermRead,
+    event::{Event, Key, MouseEvent},
+    input::{MouseTerminal, TermRead},
     raw::{IntoRawMode, RawTerminal},
 };
 use tokio::{runtime::Runtime, sync::mpsc::Sender, task::JoinHandle, time::sleep};
@@ -11,11 +18,12 @@ use tui::{
 };
 
 pub mod file;
+pub mod help;
 pub mod input;
 pub mod layout;
-pub mod spinner;
-pub mod help;
 pub mod list;
+pub mod spinner;
+pub mod template;
 
 pub enum UiStateReaction {
     Exit,
@@ -32,18 +40,36 @@ where
     fn require_ticking(&self) -> Option<Duration>;
     /// Called upon input.
     fn on_key(&mut self, key: Key) -> Option<UiStateReaction>;
+    /// Called upon a mouse event (a click, or the scroll wheel). Coordinates
+    /// are 1-based, as given by `termion`.
+    fn on_mouse(&mut self, _event: MouseEvent) -> Option<UiStateReaction> {
+        None
+    }
+    /// Called upon text being pasted into the terminal, with the whole of the
+    /// pasted text (which is not also given to `on_key`).
+    fn on_paste(&mut self, _text: String) -> Option<UiStateReaction> {
+        None
+    }
     /// Called upon a tick, which can happen at fixed intervals (as
     /// specified in `require_ticking`), or when a redraw is required
     /// for some reason.
     ///
     /// Therefore, you should not rely on this function being called
     /// *only* at fixed intervals (as specified in `require_ticking`),
-    /// as it may be called more often (for example, the terminal is
-    /// resized).
+    /// as it may be called more often (for example, when the state is
+    /// entered upon).
     ///
     /// This function is guaranteed to be called always before a `draw`
     /// call.
     fn on_tick(&mut self) -> Option<UiStateReaction>;
+    /// Called once whenever the terminal changes size, with the new
+    /// `(width, height)` of the terminal, before the following `draw`.
+    ///
+    /// States that cache layout computations should invalidate them here,
+    /// rather than recomputing them on every `draw`.
+    fn on_resize(&mut self, _size: (u16, u16)) -> Option<UiStateReaction> {
+        None
+    }
     /// Draw the current state to the provided buffer.
     ///
     /// # A Note on why This Function Takes a Mutable Reference
@@ -67,6 +93,12 @@ enum FsmEvent {
     Tick,
     /// A key was pressed.
     Key(Key),
+    /// The mouse was clicked or scrolled.
+    Mouse(MouseEvent),
+    /// Text was pasted.
+    Paste(String),
+    /// The terminal was resized to the given `(width, height)`.
+    Resize((u16, u16)),
 }
 
 enum FsmReaction {
@@ -121,6 +153,9 @@ where
         let reaction = match event {
             FsmEvent::Tick => self.state.on_tick(),
             FsmEvent::Key(k) => self.state.on_key(k),
+            FsmEvent::Mouse(m) => self.state.on_mouse(m),
+            FsmEvent::Paste(text) => self.state.on_paste(text),
+            FsmEvent::Resize(size) => self.state.on_resize(size),
         };
         if let Some(reaction) = reaction {
             match reaction {
@@ -165,15 +200,48 @@ where
     }
 }
 
-type BackendInUse = TermionBackend<RawTerminal<std::io::Stdout>>;
+/// What the terminal sends before and after pasted text, in bracketed paste mode.
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Keeps the terminal in bracketed paste mode (where pasted text is told apart
+/// from typed text) while alive.
+struct BracketedPaste;
+
+impl BracketedPaste {
+    fn enable() -> Self {
+        let mut stdout = std::io::stdout();
+        write!(stdout, "\x1b[?2004h").ok();
+        stdout.flush().ok();
+        BracketedPaste
+    }
+}
+
+impl Drop for BracketedPaste {
+    fn drop(&mut self) {
+        let mut stdout = std::io::stdout();
+        write!(stdout, "\x1b[?2004l").ok();
+        stdout.flush().ok();
+    }
+}
+
+type BackendInUse = TermionBackend<MouseTerminal<RawTerminal<std::io::Stdout>>>;
 
 pub fn run_ui(state: &mut dyn UiState<BackendInUse>) {
-    // Initialize termion/tui terminal
-    let stdout = std::io::stdout()
-        .into_raw_mode()
-        .expect("Could not get stdout in raw mode.");
-    let backend = TermionBackend::new(stdout);
-    let terminal = Terminal::new(backend).unwrap();
+    if !termion::is_tty(&std::io::stdin()) || !termion::is_tty(&std::io::stdout()) {
+        eprintln!("{}", "This command needs an interactive terminal.".red());
+        eprintln!(
+            "{} {}{} {}{} {}{}",
+            "Without one, try".dimmed(),
+            "boyl make --all".yellow(),
+            ",".dimmed(),
+            "boyl tree --plain".yellow(),
+            ", or".dimmed(),
+            "boyl rename/describe/alias".yellow(),
+            " instead of boyl edit.".dimmed()
+        );
+        std::process::exit(exitcode::USAGE);
+    }
 
     // The tokio handler for our async tasks
     let tokio_runtime = Arc::new(
@@ -183,6 +251,54 @@ pub fn run_ui(state: &mut dyn UiState<BackendInUse>) {
             .unwrap(),
     );
 
+    // If anything panics while the TUI is up, printing the message right away
+    // would garble it (the terminal is still in raw mode), and it would be
+    // drawn over anyway. Instead, messages are held back until the terminal,
+    // which restores itself when dropped, has been unwound past.
+    let deferred_panics = Arc::new(Mutex::new(Vec::<String>::new()));
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new({
+        let deferred_panics = deferred_panics.clone();
+        move |info| {
+            let thread = std::thread::current();
+            let mut message = format!("thread '{}' {}", thread.name().unwrap_or("<unnamed>"), info);
+            let backtrace = std::backtrace::Backtrace::capture();
+            if let std::backtrace::BacktraceStatus::Captured = backtrace.status() {
+                message.push_str(&format!("\n{}", backtrace));
+            }
+            deferred_panics.lock().push(message);
+        }
+    }));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        run_fsm(state, tokio_runtime.clone());
+    }));
+
+    panic::set_hook(previous_hook);
+    for message in deferred_panics.lock().drain(..) {
+        eprintln!("{}", message);
+    }
+    if let Err(payload) = result {
+        // The input thread may still be blocked reading from stdin, which would
+        // keep a regular shutdown of the runtime waiting for a key press.
+        if let Ok(tokio_runtime) = Arc::try_unwrap(tokio_runtime) {
+            tokio_runtime.shutdown_background();
+        }
+        panic::resume_unwind(payload);
+    }
+}
+
+fn run_fsm(state: &mut dyn UiState<BackendInUse>, tokio_runtime: Arc<tokio::runtime::Runtime>) {
+    // Initialize termion/tui terminal
+    let stdout = MouseTerminal::from(
+        std::io::stdout()
+            .into_raw_mode()
+            .expect("Could not get stdout in raw mode."),
+    );
+    let _bracketed_paste = BracketedPaste::enable();
+    let backend = TermionBackend::new(stdout);
+    let terminal = Terminal::new(backend).unwrap();
+
     // The channels for communication between the tokio "threads" and the FSM
     let (event_tx, event_rx) = tokio::sync::mpsc::channel::<InternalFsmEvent>(10_usize);
 
@@ -195,7 +311,7 @@ pub fn run_ui(state: &mut dyn UiState<BackendInUse>) {
 
     // The tokio task responsible for detecting terminal resizes. This is done
     // in a bit of a funky way, where we just poll the terminal size every so
-    // often, and fire a `Tick` event when we detect that it has changed since
+    // often, and fire a `Resize` event when we detect that it has changed since
     // the last poll. On any error, this task aborts.
     tokio_runtime.spawn({
         let event_tx = event_tx.clone();
@@ -211,7 +327,7 @@ pub fn run_ui(state: &mut dyn UiState<BackendInUse>) {
                 };
                 if last_seen != new_size
                     && event_tx
-                        .send(InternalFsmEvent::Bare(FsmEvent::Tick))
+                        .send(InternalFsmEvent::Bare(FsmEvent::Resize(new_size)))
                         .await
                         .is_err()
                 {
@@ -224,8 +340,9 @@ pub fn run_ui(state: &mut dyn UiState<BackendInUse>) {
         }
     });
 
-    // Thread responsible for listening to key events (which is exposed)
-    // in a blocking iterator, and dispatch the events to the main loop.
+    // Thread responsible for listening to key and mouse events (which are
+    // exposed in a blocking iterator), and dispatch them to the main loop.
+    // Pasted text is gathered, and dispatched whole.
     //
     // Tokio async stdin is not an option:
     //
@@ -239,13 +356,28 @@ pub fn run_ui(state: &mut dyn UiState<BackendInUse>) {
     //      to user input and use blocking IO directly in that thread.
     tokio_runtime.spawn_blocking(move || {
         let stdin = std::io::stdin();
-        for key in stdin.keys().flatten() {
+        let mut pasted: Option<String> = None;
+        for event in stdin.events().flatten() {
+            let event = match (event, &mut pasted) {
+                (Event::Unsupported(bytes), _) if bytes == PASTE_START => {
+                    pasted = Some(String::new());
+                    continue;
+                }
+                (Event::Unsupported(bytes), Some(_)) if bytes == PASTE_END => {
+                    FsmEvent::Paste(pasted.take().unwrap())
+                }
+                (Event::Key(Key::Char(c)), Some(text)) => {
+                    text.push(c);
+                    continue;
+                }
+                (_, Some(_)) => continue,
+                (Event::Key(key), None) => FsmEvent::Key(key),
+                (Event::Mouse(mouse), None) => FsmEvent::Mouse(mouse),
+                (Event::Unsupported(_), None) => continue,
+            };
             let (inquire_tx, inquire_rx) = oneshot::channel::<bool>();
             if event_tx
-                .blocking_send(InternalFsmEvent::InquireTerminate(
-                    FsmEvent::Key(key),
-                    inquire_tx,
-                ))
+                .blocking_send(InternalFsmEvent::InquireTerminate(event, inquire_tx))
                 .is_err()
             {
                 // Main loop has hung up, goodbye!
@@ -282,7 +414,7 @@ pub fn run_ui(state: &mut dyn UiState<BackendInUse>) {
                 state_fsm.draw(f);
             });
             if let Err(e) = draw_result {
-                println!("Failed to draw TUI with error {:?}", e)
+                eprintln!("Failed to draw TUI with error {:?}", e)
             };
         }
         terminal.clear().unwrap();
@@ -1,20 +1,41 @@
-use std::str;
+use std::{
+    str,
+    time::{Duration, Instant},
+};
 
-const SPINNER_CHARS: &[&str] = &[
-    "⠉", "⠋", "⠍", "⠎", "⡅", "⡆", "⣄", "⣠", "⣈", "⣘", "⢱",
-];
+const SPINNER_CHARS: &[&str] = &["⠉", "⠋", "⠍", "⠎", "⡅", "⡆", "⣄", "⣠", "⣈", "⣘", "⢱"];
 
 pub struct Spinner {
     idx: usize,
+    started: Instant,
+    enabled: bool,
 }
 
 impl Spinner {
+    /// Creates a spinner, which is only enabled if stderr (where progress goes) is a terminal and the
+    /// `BOYL_NO_SPINNER` environment variable is not set.
     pub fn new() -> Self {
-        Spinner { idx: 0 }
+        Spinner {
+            idx: 0,
+            started: Instant::now(),
+            enabled: termion::is_tty(&std::io::stderr())
+                && std::env::var_os("BOYL_NO_SPINNER").is_none(),
+        }
+    }
+
+    /// Whether progress can be drawn in place (overwriting itself); otherwise,
+    /// e.g. when writing to a log, it should be printed in plain lines, if at all.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// How long it has been since the spinner was created.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
     }
 
     pub fn tick(&mut self) -> &'static str {
         self.idx = (self.idx + 1) % SPINNER_CHARS.len();
-        &SPINNER_CHARS[self.idx]
+        SPINNER_CHARS[self.idx]
     }
 }
@@ -0,0 +1,191 @@
+use crate::{
+    config::{Config, TemplateKey},
+    template::Template,
+    ui::{self, input::InputField, layout::VisualBox, list::List, UiState, UiStateReaction},
+};
+use termion::event::Key;
+use tui::{
+    backend::Backend,
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders},
+};
+
+/// Computes a single `Spans`, corresponding to one entry on a list of templates.
+pub fn template_entry(template: &Template) -> Spans<'static> {
+    let mut spans = vec![Span::raw(template.name.clone())];
+    if let Some(version) = &template.template_version {
+        spans.push(Span::styled(
+            format!(" [{}]", version),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    spans.extend(vec![
+        Span::raw(" "),
+        Span::styled(
+            template
+                .description
+                .as_deref()
+                .unwrap_or("(No description.)")
+                .to_string(),
+            Style::default().fg(Color::Gray),
+        ),
+    ]);
+    Spans::from(spans)
+}
+
+/// A list of the templates to choose one from, which can be narrowed by typing a
+/// filter, as in `edit`.
+pub struct TemplatePickerUi<'conf> {
+    config: &'conf Config,
+    list: List<'conf, Spans<'conf>>,
+    input: InputField,
+    /// Only templates whose name or description contain this (ignoring case) are
+    /// listed.
+    filter: String,
+    /// Whether the filter is being typed.
+    filtering: bool,
+    /// The template chosen, once the picker is exited with one.
+    pub picked: Option<TemplateKey>,
+}
+
+impl<'conf> TemplatePickerUi<'conf> {
+    pub fn new(config: &'conf Config) -> Self {
+        TemplatePickerUi {
+            config,
+            list: List::new(Self::make_list_elements(config, "")),
+            input: InputField::new(),
+            filter: String::new(),
+            filtering: false,
+            picked: None,
+        }
+    }
+
+    fn make_list_elements(config: &Config, filter: &str) -> Vec<Spans<'static>> {
+        config
+            .templates_matching(filter)
+            .into_iter()
+            .map(|(_, template)| template_entry(template))
+            .collect()
+    }
+
+    /// Recomputes the list's elements after the filter changed, keeping the
+    /// highlight within the list.
+    fn refresh_list(&mut self) {
+        let highlight = self.list.highlight;
+        self.list = List::new(Self::make_list_elements(self.config, &self.filter));
+        self.list.highlight = std::cmp::min(highlight, self.list.len().saturating_sub(1));
+    }
+
+    fn list_input(&mut self, key: Key) -> Option<UiStateReaction> {
+        match key {
+            Key::Up | Key::Char('k') => self.list.go_up(),
+            Key::Down | Key::Char('j') => self.list.go_down(),
+            Key::PageUp => self.list.go_page_up(self.list.last_height()),
+            Key::PageDown => self.list.go_page_down(self.list.last_height()),
+            Key::Home => self.list.go_first(),
+            Key::End => self.list.go_last(),
+            Key::Char('\n') | Key::Char('\r') if self.list.len() > 0 => {
+                let (key, _) = self.config.templates_matching(&self.filter)[self.list.highlight];
+                self.picked = Some(key);
+                return Some(UiStateReaction::Exit);
+            }
+            Key::Char('/') => {
+                self.input = InputField::new_with_content(self.filter.clone());
+                self.filtering = true;
+            }
+            Key::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.refresh_list();
+            }
+            Key::Esc | Key::Char('q') | Key::Ctrl('c') => return Some(UiStateReaction::Exit),
+            _ => {}
+        }
+        None
+    }
+
+    fn filter_input(&mut self, key: Key) -> Option<UiStateReaction> {
+        match key {
+            Key::Esc | Key::Ctrl('c') => {
+                self.input = InputField::new();
+                self.filtering = false;
+            }
+            Key::Char('\n') | Key::Char('\r') => {
+                self.filtering = false;
+                return None;
+            }
+            Key::Left => self.input.caret_move_left(),
+            Key::Right => self.input.caret_move_right(),
+            Key::Alt('b') => self.input.caret_move_word_left(),
+            Key::Alt('f') => self.input.caret_move_word_right(),
+            Key::Ctrl('u') => self.input.clear_to_start(),
+            Key::Ctrl('k') => self.input.clear_to_end(),
+            Key::Backspace => self.input.backspace_char(),
+            Key::Delete => self.input.delete_char(),
+            Key::Char(c) => self.input.add_char(c),
+            _ => return None,
+        }
+        self.filter = self.input.consume_input();
+        self.refresh_list();
+        None
+    }
+}
+
+impl<'conf, B: Backend> UiState<B> for TemplatePickerUi<'conf> {
+    fn require_ticking(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    fn on_key(&mut self, key: Key) -> Option<UiStateReaction> {
+        if self.filtering {
+            self.filter_input(key)
+        } else {
+            self.list_input(key)
+        }
+    }
+
+    fn on_paste(&mut self, text: String) -> Option<UiStateReaction> {
+        if self.filtering {
+            self.input.paste(&text);
+            self.filter = self.input.consume_input();
+            self.refresh_list();
+        }
+        None
+    }
+
+    fn on_tick(&mut self) -> Option<UiStateReaction> {
+        None
+    }
+
+    fn draw(&mut self, f: &mut tui::Frame<B>) {
+        let size = f.size();
+        let remaining = if self.filtering {
+            ui::input::draw_input(f, size, &mut self.input, "Filter: ")
+        } else {
+            let mut helps = vec![
+                ui::help::make_help_box("Up/K", "Move up in list"),
+                ui::help::make_help_box("Down/J", "Move down in list"),
+                ui::help::make_help_box("PgUp/PgDn", "Move a page"),
+                ui::help::make_help_box("Home/End", "First/Last entry"),
+                ui::help::make_help_box("/", "Filter"),
+            ];
+            if !self.filter.is_empty() {
+                helps.push(ui::help::make_help_box("Esc", "Clear filter"));
+            }
+            helps.push(ui::help::make_help_box("Enter", "Use template"));
+            helps.push(ui::help::make_help_box("Q", "Cancel"));
+            let (help_texts, help_boxes): (Vec<ui::help::HelpText>, Vec<VisualBox>) =
+                helps.into_iter().unzip();
+            ui::help::draw_help(help_texts, help_boxes, f, size)
+        };
+        let title = if self.filter.is_empty() {
+            "Templates:".to_string()
+        } else {
+            format!("Templates matching '{}':", self.filter)
+        };
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let block_inner = block.inner(remaining);
+        f.render_widget(block, remaining);
+        self.list.draw(f, block_inner);
+    }
+}
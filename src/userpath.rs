@@ -71,6 +71,31 @@ impl From<PathBuf> for UserDir {
     }
 }
 
+/// A path provided by the user, with `~` and environment variables expanded.
+///
+/// Unlike [`UserDir`], the path is not required to exist (or to be a directory).
+#[derive(Clone, PartialEq, Eq)]
+pub struct UserPath {
+    pub path_buf: PathBuf,
+}
+
+impl std::fmt::Debug for UserPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.path_buf.fmt(f)
+    }
+}
+
+impl FromStr for UserPath {
+    type Err = UserDirErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expanded = shellexpand::full(s)?;
+        // <PathBuf as FromStr>::Err is infallible
+        let path_buf = PathBuf::from_str(&expanded).unwrap();
+        Ok(UserPath { path_buf })
+    }
+}
+
 impl std::fmt::Display for UserDir {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.path_buf.to_string_lossy())
@@ -82,3 +107,21 @@ impl std::fmt::Display for UserDir {
 pub fn to_user_path(path: &str) -> Result<UserDir, String> {
     UserDir::from_str(path).map_err(|e| e.to_string())
 }
+
+/// The current working directory. If it cannot be determined (e.g., because it
+/// has since been removed), a message is printed and the process exits.
+pub fn current_dir_or_exit() -> PathBuf {
+    std::env::current_dir().unwrap_or_else(|err| {
+        eprintln!(
+            "{}",
+            format!("Could not determine the current directory: {}", err).red()
+        );
+        eprintln!(
+            "{} {}{}",
+            "It may have been removed. Try again from another directory, or give one with".dimmed(),
+            "--location".yellow(),
+            ".".dimmed()
+        );
+        std::process::exit(exitcode::OSERR);
+    })
+}
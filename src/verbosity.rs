@@ -0,0 +1,22 @@
+/// How much a command reports about what it is doing, as given by the number of
+/// `-v` switches.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum Verbosity {
+    /// Only the usual output.
+    #[default]
+    None,
+    /// Also every file that is copied.
+    Some,
+    /// Also whether each file is included, and why.
+    Very,
+}
+
+impl From<u8> for Verbosity {
+    fn from(count: u8) -> Self {
+        match count {
+            0 => Verbosity::None,
+            1 => Verbosity::Some,
+            _ => Verbosity::Very,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Versions of boyl, as written to configuration files.
+
+/// A `major.minor.patch` version. Versions are ordered by their components, as
+/// numbers; pre-release and build suffixes (as in `0.2.0-beta`) are ignored.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    /// Parses a version such as `1.2.3`, `1.2.3-rc.1` or `1.2`. Missing minor or
+    /// patch components are taken to be 0. Returns `None` if the major component
+    /// is not a number.
+    pub fn parse(version: &str) -> Option<Version> {
+        let version = version.trim().trim_start_matches('v');
+        // Everything after a pre-release or build marker is ignored.
+        let core = version.split(['-', '+']).next().unwrap();
+        let mut components = core.split('.').map(|component| component.parse::<u64>());
+        let major = components.next()?.ok()?;
+        let minor = components.next().and_then(Result::ok).unwrap_or(0);
+        let patch = components.next().and_then(Result::ok).unwrap_or(0);
+        Some(Version {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    /// The version of this build of boyl.
+    pub fn current() -> Version {
+        Version::parse(crate::VERSION).expect("The crate version is not a valid version.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_as_numbers() {
+        assert!(Version::parse("1.2.0") < Version::parse("1.10.0"));
+        assert!(Version::parse("0.9.9") < Version::parse("0.10.0"));
+        assert!(Version::parse("2.0.0") > Version::parse("1.99.99"));
+    }
+
+    #[test]
+    fn suffixes_are_ignored() {
+        assert_eq!(Version::parse("0.2.0-beta"), Version::parse("0.2.0"));
+        assert_eq!(Version::parse("v1.2.3+build.5"), Version::parse("1.2.3"));
+    }
+
+    #[test]
+    fn missing_components_are_zero() {
+        assert_eq!(Version::parse("1.2"), Version::parse("1.2.0"));
+        assert_eq!(Version::parse("not a version"), None);
+    }
+}
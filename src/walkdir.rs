@@ -5,35 +5,95 @@
 /// [0]: https://stackoverflow.com/a/58825638
 use futures::StreamExt; // 0.3.1
 use futures::{stream, Stream};
-use std::{io, path::PathBuf};
-use tokio::fs::{self, DirEntry}; // 0.2.4
+use std::{collections::HashSet, fs::Metadata, io, path::PathBuf};
+use tokio::fs; // 0.2.4
 
+/// A file found while walking a directory tree, along with its metadata, so
+/// that consumers of the walk do not need to query the file system again.
+pub struct WalkEntry {
+    pub path: PathBuf,
+    pub metadata: Metadata,
+}
+
+/// Directories still to be walked, and the (canonical) directories that have
+/// already been queued, so that no directory is walked twice.
+struct WalkState {
+    to_visit: Vec<PathBuf>,
+    visited: HashSet<PathBuf>,
+}
+
+impl WalkState {
+    /// Queues `path` to be walked, unless it (or whatever it resolves to) has
+    /// already been queued.
+    async fn queue(&mut self, path: PathBuf) {
+        let canonical = fs::canonicalize(&path)
+            .await
+            .unwrap_or_else(|_| path.clone());
+        if self.visited.insert(canonical) {
+            self.to_visit.push(path);
+        }
+    }
+}
+
+/// Walks the tree under `path`.
+///
+/// Symbolic links are given as they are (with their own metadata), unless
+/// `follow_symlinks` is set, in which case they are given as whatever they point
+/// to, and linked directories are walked as well. Directories that were already
+/// walked are not walked again, so that links cannot make the walk loop forever.
 pub fn visit(
     path: impl Into<PathBuf>,
-) -> impl Stream<Item = io::Result<DirEntry>> + Send + 'static {
-    async fn one_level(path: PathBuf, to_visit: &mut Vec<PathBuf>) -> io::Result<Vec<DirEntry>> {
+    follow_symlinks: bool,
+) -> impl Stream<Item = io::Result<WalkEntry>> + Send + 'static {
+    async fn one_level(
+        path: PathBuf,
+        follow_symlinks: bool,
+        state: &mut WalkState,
+    ) -> io::Result<Vec<WalkEntry>> {
         let mut dir = fs::read_dir(path).await?;
         let mut files = Vec::new();
 
         while let Some(child) = dir.next_entry().await? {
-            if child.metadata().await?.is_dir() {
-                to_visit.push(child.path());
+            let metadata = if follow_symlinks {
+                // Broken links are given as links.
+                match fs::metadata(child.path()).await {
+                    Ok(metadata) => metadata,
+                    Err(_) => fs::symlink_metadata(child.path()).await?,
+                }
+            } else {
+                fs::symlink_metadata(child.path()).await?
+            };
+            if metadata.is_dir() {
+                state.queue(child.path()).await;
             }
             // We also want to copy directories, even if they are empty.
-            files.push(child)
+            files.push(WalkEntry {
+                path: child.path(),
+                metadata,
+            })
         }
 
         Ok(files)
     }
 
-    stream::unfold(vec![path.into()], |mut to_visit| async {
-        let path = to_visit.pop()?;
-        let file_stream = match one_level(path, &mut to_visit).await {
-            Ok(files) => stream::iter(files).map(Ok).left_stream(),
-            Err(e) => stream::once(async { Err(e) }).right_stream(),
-        };
+    let state = WalkState {
+        to_visit: vec![],
+        visited: HashSet::new(),
+    };
+    stream::unfold(
+        (Some(path.into()), state),
+        move |(root, mut state)| async move {
+            if let Some(root) = root {
+                state.queue(root).await;
+            }
+            let path = state.to_visit.pop()?;
+            let file_stream = match one_level(path, follow_symlinks, &mut state).await {
+                Ok(files) => stream::iter(files).map(Ok).left_stream(),
+                Err(e) => stream::once(async { Err(e) }).right_stream(),
+            };
 
-        Some((file_stream, to_visit))
-    })
+            Some((file_stream, (None, state)))
+        },
+    )
     .flatten()
 }